@@ -1,6 +1,8 @@
 use proc_macro2::{Span, TokenStream};
+use quote::format_ident;
 use syn::{
-    Attribute, Error, Ident, Path, Token, Type, Visibility,
+    Attribute, Error, Expr, ExprLit, Ident, Lit, LitInt, LitStr, Path, Token, Type, Visibility,
+    bracketed,
     ext::IdentExt,
     parse::{Parse, ParseStream, Result},
     parse_quote,
@@ -15,12 +17,31 @@ use syn::{
 /// - `#[extern_trait(pub ProxyName)]`
 /// - `#[extern_trait(crate = path)]`
 /// - `#[extern_trait(default = Type, ProxyName)]`
+/// - `#[extern_trait(instances = [Trait<A>, Trait<B>], ProxyName)]`
+/// - `#[extern_trait(size = N, align = A, ProxyName)]`
+/// - `#[extern_trait(non_null, ProxyName)]`
+/// - `#[extern_trait(zero_sized, ProxyName)]`
+/// - `#[extern_trait(service, ServiceName)]`
+/// - `#[extern_trait(symbol = "name", ProxyName)]`
+/// - `#[extern_trait(direct, ProxyName)]`
+/// - `#[extern_trait(abi = "C", ProxyName)]`
 /// - `#[extern_trait(crate = path, ProxyName)]`
 /// - `#[extern_trait(ProxyName, crate = path)]`
+/// - `#[extern_trait(boxed)]` on an impl
 pub struct Args {
     extern_trait: Path,
     proxy: Option<Proxy>,
     default: Option<Type>,
+    instances: Option<Vec<Path>>,
+    size: Option<Expr>,
+    align: Option<LitInt>,
+    non_null: Option<Ident>,
+    zero_sized: Option<Ident>,
+    service: Option<Ident>,
+    symbol: Option<LitStr>,
+    direct: Option<Ident>,
+    abi: Option<LitStr>,
+    boxed: Option<Ident>,
 }
 
 impl Parse for Args {
@@ -29,6 +50,16 @@ impl Parse for Args {
         let mut extern_trait = None;
         let mut proxy = None;
         let mut default = None;
+        let mut instances = None;
+        let mut size = None;
+        let mut align = None;
+        let mut non_null = None;
+        let mut zero_sized = None;
+        let mut service = None;
+        let mut symbol = None;
+        let mut direct = None;
+        let mut abi = None;
+        let mut boxed = None;
         for arg in args {
             match arg {
                 Arg::Crate(path) => {
@@ -43,6 +74,66 @@ impl Parse for Args {
                     }
                     default = Some(ty);
                 }
+                Arg::Instances(key, paths) => {
+                    if instances.is_some() {
+                        return Err(Error::new_spanned(key, "duplicate `instances` argument"));
+                    }
+                    instances = Some(paths);
+                }
+                Arg::Size(expr) => {
+                    if size.is_some() {
+                        return Err(Error::new_spanned(expr, "duplicate `size` argument"));
+                    }
+                    size = Some(expr);
+                }
+                Arg::Align(lit) => {
+                    if align.is_some() {
+                        return Err(Error::new_spanned(lit, "duplicate `align` argument"));
+                    }
+                    align = Some(lit);
+                }
+                Arg::NonNull(ident) => {
+                    if non_null.is_some() {
+                        return Err(Error::new_spanned(ident, "duplicate `non_null` argument"));
+                    }
+                    non_null = Some(ident);
+                }
+                Arg::ZeroSized(ident) => {
+                    if zero_sized.is_some() {
+                        return Err(Error::new_spanned(ident, "duplicate `zero_sized` argument"));
+                    }
+                    zero_sized = Some(ident);
+                }
+                Arg::Service(ident) => {
+                    if service.is_some() {
+                        return Err(Error::new_spanned(ident, "duplicate `service` argument"));
+                    }
+                    service = Some(ident);
+                }
+                Arg::Symbol(lit) => {
+                    if symbol.is_some() {
+                        return Err(Error::new_spanned(lit, "duplicate `symbol` argument"));
+                    }
+                    symbol = Some(lit);
+                }
+                Arg::Direct(ident) => {
+                    if direct.is_some() {
+                        return Err(Error::new_spanned(ident, "duplicate `direct` argument"));
+                    }
+                    direct = Some(ident);
+                }
+                Arg::Abi(lit) => {
+                    if abi.is_some() {
+                        return Err(Error::new_spanned(lit, "duplicate `abi` argument"));
+                    }
+                    abi = Some(lit);
+                }
+                Arg::Boxed(ident) => {
+                    if boxed.is_some() {
+                        return Err(Error::new_spanned(ident, "duplicate `boxed` argument"));
+                    }
+                    boxed = Some(ident);
+                }
                 Arg::Proxy(value) => {
                     if proxy.is_some() {
                         return Err(Error::new_spanned(
@@ -59,15 +150,37 @@ impl Parse for Args {
             extern_trait: extern_trait.unwrap_or_else(|| parse_quote!(::extern_trait)),
             proxy,
             default,
+            instances,
+            size,
+            align,
+            non_null,
+            zero_sized,
+            service,
+            symbol,
+            direct,
+            abi,
+            boxed,
         })
     }
 }
 
 /// Validated arguments for `#[extern_trait(...)]` on a trait declaration.
+#[derive(Clone)]
 pub struct TraitArgs {
     pub extern_trait: Path,
     pub proxy: Proxy,
     pub default: Option<Type>,
+    /// Instantiations of a generic trait, e.g. `Codec<u32>`.
+    pub instances: Vec<Path>,
+    pub layout: Layout,
+    /// Generate a unit type of associated functions rather than a proxy.
+    pub service: bool,
+    /// VTable symbol set with `symbol = "..."` in place of the derived one.
+    pub symbol: Option<String>,
+    /// Export one symbol per method rather than calling through the VTable.
+    pub direct: bool,
+    /// Use the C ABI for the VTable, set with `abi = "C"`.
+    pub c_abi: bool,
 }
 
 impl TryFrom<Args> for TraitArgs {
@@ -82,25 +195,160 @@ impl TryFrom<Args> for TraitArgs {
         })?;
 
         if let Some(default) = &args.default
-            && !cfg!(feature = "nightly-weak")
+            && !cfg!(any(feature = "section-defaults", feature = "nightly-weak"))
         {
             return Err(Error::new_spanned(
                 default,
-                "`default = ...` requires the `nightly-weak` feature and `#![feature(linkage)]`",
+                "`default = ...` requires the `section-defaults` feature, or the `nightly-weak` \
+                 feature and `#![feature(linkage)]`",
+            ));
+        }
+
+        if let Some(boxed) = args.boxed {
+            return Err(Error::new_spanned(
+                boxed,
+                "`boxed` is only supported on trait impls",
             ));
         }
 
+        if let Some(align) = &args.align {
+            let value = align.base10_parse::<usize>()?;
+            if !value.is_power_of_two() || value > 4096 {
+                return Err(Error::new_spanned(
+                    align,
+                    "`align` must be a power of two no greater than 4096",
+                ));
+            }
+        }
+
+        if let Some(non_null) = &args.non_null
+            && (args.size.is_some() || args.align.is_some())
+        {
+            return Err(Error::new_spanned(
+                non_null,
+                "`non_null` cannot be combined with `size` or `align`",
+            ));
+        }
+
+        if let Some(zero_sized) = &args.zero_sized
+            && (args.size.is_some() || args.align.is_some() || args.non_null.is_some())
+        {
+            return Err(Error::new_spanned(
+                zero_sized,
+                "`zero_sized` cannot be combined with `size`, `align` or `non_null`",
+            ));
+        }
+
+        if let Some(service) = &args.service
+            && (args.instances.is_some()
+                || args.size.is_some()
+                || args.align.is_some()
+                || args.non_null.is_some()
+                || args.zero_sized.is_some())
+        {
+            return Err(Error::new_spanned(
+                service,
+                "`service` cannot be combined with `instances`, `size`, `align`, `non_null` or \
+                 `zero_sized`",
+            ));
+        }
+
+        if let Some(direct) = &args.direct
+            && args.default.is_some()
+        {
+            return Err(Error::new_spanned(
+                direct,
+                "`direct` cannot be combined with `default`",
+            ));
+        }
+
+        let c_abi = match &args.abi {
+            Some(abi) if abi.value() == "C" => true,
+            Some(abi) if abi.value() == "Rust" => false,
+            Some(abi) => {
+                return Err(Error::new_spanned(abi, "`abi` must be \"C\" or \"Rust\""));
+            }
+            None => false,
+        };
+        if let Some(abi) = &args.abi
+            && c_abi
+        {
+            if args.default.is_some()
+                || args.direct.is_some()
+                || args.instances.is_some()
+                || args.zero_sized.is_some()
+            {
+                return Err(Error::new_spanned(
+                    abi,
+                    "`abi = \"C\"` cannot be combined with `default`, `direct`, `instances` or \
+                     `zero_sized`",
+                ));
+            }
+            if let Some(size) = &args.size
+                && !matches!(
+                    size,
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Int(_),
+                        ..
+                    })
+                )
+            {
+                return Err(Error::new_spanned(
+                    size,
+                    "`abi = \"C\"` requires `size` to be an integer literal",
+                ));
+            }
+        }
+
+        if let Some(symbol) = &args.symbol {
+            if args.instances.is_some() {
+                return Err(Error::new_spanned(
+                    symbol,
+                    "`symbol` cannot be combined with `instances`",
+                ));
+            }
+            let value = symbol.value();
+            let valid = value
+                .bytes()
+                .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'$' | b'.'));
+            if !valid || !value.starts_with(|c: char| !c.is_ascii_digit()) {
+                return Err(Error::new_spanned(
+                    symbol,
+                    "`symbol` must be ASCII alphanumerics, `_`, `$` and `.`, not starting with a \
+                     digit",
+                ));
+            }
+        }
+
         Ok(TraitArgs {
             extern_trait: args.extern_trait,
             proxy,
             default: args.default,
+            instances: args.instances.unwrap_or_default(),
+            layout: Layout {
+                size: args.size,
+                align: args.align,
+                non_null: args.non_null.is_some(),
+                // Service implementations are never constructed, so they
+                // must be zero-sized, with their own error message.
+                zero_sized: args.zero_sized.is_some() || args.service.is_some(),
+            },
+            service: args.service.is_some(),
+            symbol: args.symbol.map(|symbol| symbol.value()),
+            direct: args.direct.is_some(),
+            c_abi,
         })
     }
 }
 
 /// Validated arguments for `#[extern_trait(...)]` on an impl block.
+///
+/// Besides `boxed`, only `crate = path` is allowed. The impl does not need
+/// it, since the trait's macro emits the VTable, but it is accepted so that
+/// both sides can be written alike.
 pub struct ImplArgs {
-    pub extern_trait: Path,
+    /// Store the implementation in a `Box`, for types too large for `Repr`.
+    pub boxed: bool,
 }
 
 impl TryFrom<Args> for ImplArgs {
@@ -114,6 +362,13 @@ impl TryFrom<Args> for ImplArgs {
             ));
         }
 
+        if let Some(instance) = args.instances.iter().flatten().next() {
+            return Err(Error::new_spanned(
+                instance,
+                "instances are only supported on trait declarations",
+            ));
+        }
+
         if let Some(proxy) = args.proxy {
             return Err(Error::new_spanned(
                 proxy.ident,
@@ -121,12 +376,115 @@ impl TryFrom<Args> for ImplArgs {
             ));
         }
 
+        if let Some(size) = args.size {
+            return Err(Error::new_spanned(
+                size,
+                "`size` is only supported on trait declarations",
+            ));
+        }
+
+        if let Some(align) = args.align {
+            return Err(Error::new_spanned(
+                align,
+                "`align` is only supported on trait declarations",
+            ));
+        }
+
+        if let Some(non_null) = args.non_null {
+            return Err(Error::new_spanned(
+                non_null,
+                "`non_null` is only supported on trait declarations",
+            ));
+        }
+
+        if let Some(zero_sized) = args.zero_sized {
+            return Err(Error::new_spanned(
+                zero_sized,
+                "`zero_sized` is only supported on trait declarations",
+            ));
+        }
+
+        if let Some(service) = args.service {
+            return Err(Error::new_spanned(
+                service,
+                "`service` is only supported on trait declarations",
+            ));
+        }
+
+        if let Some(symbol) = args.symbol {
+            return Err(Error::new_spanned(
+                symbol,
+                "`symbol` is only supported on trait declarations",
+            ));
+        }
+
+        if let Some(direct) = args.direct {
+            return Err(Error::new_spanned(
+                direct,
+                "`direct` is only supported on trait declarations",
+            ));
+        }
+
+        if let Some(abi) = args.abi {
+            return Err(Error::new_spanned(
+                abi,
+                "`abi` is only supported on trait declarations",
+            ));
+        }
+
+        if let Some(boxed) = &args.boxed
+            && !cfg!(feature = "alloc")
+        {
+            return Err(Error::new_spanned(
+                boxed,
+                "`boxed` requires the `alloc` feature",
+            ));
+        }
+
         Ok(ImplArgs {
-            extern_trait: args.extern_trait,
+            boxed: args.boxed.is_some(),
         })
     }
 }
 
+/// Inline storage of the proxy, set by `size = N`, `align = A`, `non_null`
+/// or `zero_sized`.
+#[derive(Clone, Default)]
+pub struct Layout {
+    pub size: Option<Expr>,
+    pub align: Option<LitInt>,
+    /// Store a non-null first word, giving the proxy a niche.
+    pub non_null: bool,
+    /// Store nothing, for traits implemented by zero-sized types.
+    pub zero_sized: bool,
+}
+
+impl Layout {
+    /// `Repr`, `NonNullRepr`, or the `SizedRepr` of a trait that sets its
+    /// size or alignment or is zero-sized.
+    pub fn repr_type(&self, extern_trait: &Path) -> Type {
+        if self.zero_sized {
+            return parse_quote!(#extern_trait::SizedRepr<0, #extern_trait::__private::Align1>);
+        }
+        if self.non_null {
+            return parse_quote!(#extern_trait::NonNullRepr);
+        }
+        if self.size.is_none() && self.align.is_none() {
+            return parse_quote!(#extern_trait::Repr);
+        }
+        let size = match &self.size {
+            Some(size) => quote::quote!({ #size }),
+            None => quote::quote!({ ::core::mem::size_of::<#extern_trait::Repr>() }),
+        };
+        let align = match &self.align {
+            Some(align) => format_ident!("Align{}", align.base10_digits()),
+            None => format_ident!("AlignRepr"),
+        };
+        parse_quote!(#extern_trait::SizedRepr<#size, #extern_trait::__private::#align>)
+    }
+}
+
+#[derive(Clone)]
 pub struct Proxy {
     pub attrs: Vec<Attribute>,
     pub vis: Visibility,
@@ -134,13 +492,23 @@ pub struct Proxy {
 }
 
 impl Proxy {
-    pub fn expand(&self, extern_trait: &Path) -> TokenStream {
+    pub fn expand(&self, repr: &Type) -> TokenStream {
         let Proxy { attrs, vis, ident } = self;
 
         quote::quote! {
             #(#attrs)*
             #[repr(transparent)]
-            #vis struct #ident(#extern_trait::Repr);
+            #vis struct #ident(#repr);
+        }
+    }
+
+    /// The unit type of a service trait.
+    pub fn expand_unit(&self) -> TokenStream {
+        let Proxy { attrs, vis, ident } = self;
+
+        quote::quote! {
+            #(#attrs)*
+            #vis struct #ident;
         }
     }
 }
@@ -148,6 +516,16 @@ impl Proxy {
 enum Arg {
     Crate(Path),
     Default(Type),
+    Instances(Ident, Vec<Path>),
+    Size(Expr),
+    Align(LitInt),
+    NonNull(Ident),
+    ZeroSized(Ident),
+    Service(Ident),
+    Symbol(LitStr),
+    Direct(Ident),
+    Abi(LitStr),
+    Boxed(Ident),
     Proxy(Proxy),
 }
 
@@ -167,10 +545,37 @@ impl Parse for Arg {
             return match key.to_string().as_str() {
                 "crate" => Ok(Self::Crate(input.call(Path::parse_mod_style)?)),
                 "default" => Ok(Self::Default(input.parse()?)),
+                "instances" => {
+                    let content;
+                    bracketed!(content in input);
+                    let paths = Punctuated::<Path, Token![,]>::parse_terminated(&content)?;
+                    Ok(Self::Instances(key, paths.into_iter().collect()))
+                }
+                "size" => Ok(Self::Size(input.parse()?)),
+                "align" => Ok(Self::Align(input.parse()?)),
+                "symbol" => Ok(Self::Symbol(input.parse()?)),
+                "abi" => Ok(Self::Abi(input.parse()?)),
                 _ => Err(Error::new_spanned(key, "unknown #[extern_trait] argument")),
             };
         }
 
+        let ahead = input.fork();
+        if let Ok(flag) = ahead.call(Ident::parse_any)
+            && (ahead.is_empty() || ahead.peek(Token![,]))
+        {
+            let flag: Option<fn(Ident) -> Self> = match flag.to_string().as_str() {
+                "boxed" => Some(Self::Boxed),
+                "non_null" => Some(Self::NonNull),
+                "zero_sized" => Some(Self::ZeroSized),
+                "service" => Some(Self::Service),
+                "direct" => Some(Self::Direct),
+                _ => None,
+            };
+            if let Some(flag) = flag {
+                return Ok(flag(input.call(Ident::parse_any)?));
+            }
+        }
+
         Ok(Self::Proxy(Proxy {
             attrs: input.call(Attribute::parse_outer)?,
             vis: input.parse()?,
@@ -0,0 +1,252 @@
+use std::fmt::Write;
+
+use syn::{Error, Expr, GenericArgument, Ident, Lit, PathArguments, Result, Type};
+
+use super::types::{MaybeSelf, SelfKind};
+use crate::args::Layout;
+
+const UNSUPPORTED: &str = "#[extern_trait(abi = \"C\")] methods may only take and return FFI-safe \
+                           types: primitives, pointers, references to sized types and `Option`s \
+                           of them, and other C-compatible types by name";
+
+/// C keywords that may be Rust method names.
+const KEYWORDS: &[&str] = &[
+    "auto", "bool", "case", "char", "default", "do", "double", "float", "goto", "inline", "int",
+    "long", "register", "restrict", "short", "signed", "sizeof", "switch", "typedef", "union",
+    "unsigned", "void", "volatile",
+];
+
+/// `name`, with a trailing `_` if it is a C keyword.
+pub fn c_ident(name: &str) -> String {
+    match KEYWORDS.contains(&name) {
+        true => format!("{name}_"),
+        false => name.to_string(),
+    }
+}
+
+fn primitive(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "u8" => "uint8_t",
+        "u16" => "uint16_t",
+        "u32" => "uint32_t",
+        "u64" => "uint64_t",
+        "usize" => "uintptr_t",
+        "i8" => "int8_t",
+        "i16" => "int16_t",
+        "i32" => "int32_t",
+        "i64" => "int64_t",
+        "isize" => "intptr_t",
+        "f32" | "c_float" => "float",
+        "f64" | "c_double" => "double",
+        "bool" => "bool",
+        "c_void" => "void",
+        "c_char" => "char",
+        "c_schar" => "signed char",
+        "c_uchar" => "unsigned char",
+        "c_short" => "short",
+        "c_ushort" => "unsigned short",
+        "c_int" => "int",
+        "c_uint" => "unsigned int",
+        "c_long" => "long",
+        "c_ulong" => "unsigned long",
+        "c_longlong" => "long long",
+        "c_ulonglong" => "unsigned long long",
+        _ => return None,
+    })
+}
+
+/// A pointer to `elem`, with `const` after the pointee so that pointers
+/// to pointers read alike.
+fn pointer(elem: String, mutable: bool) -> String {
+    match mutable {
+        true => format!("{elem} *"),
+        false => format!("{elem} const *"),
+    }
+}
+
+/// The single type argument of a path segment, e.g. `T` of `Option<T>`.
+fn type_arg(arguments: &PathArguments) -> Option<&Type> {
+    let PathArguments::AngleBracketed(args) = arguments else {
+        return None;
+    };
+    match args.args.iter().collect::<Vec<_>>().as_slice() {
+        [GenericArgument::Type(ty)] => Some(ty),
+        _ => None,
+    }
+}
+
+/// The C spelling of `ty`, a type without `Self`, or an error if it is not
+/// FFI-safe.
+///
+/// Types other than primitives and pointers are spelled by name, and must
+/// be declared in C before the header. That they are FFI-safe is left to
+/// the `improper_ctypes_definitions` lint.
+pub fn c_type(ty: &Type) -> Result<String> {
+    let unsupported = || Error::new_spanned(ty, UNSUPPORTED);
+    match ty {
+        Type::Paren(paren) => c_type(&paren.elem),
+        Type::Group(group) => c_type(&group.elem),
+        Type::Ptr(ptr) => Ok(pointer(c_type(&ptr.elem)?, ptr.mutability.is_some())),
+        Type::Reference(reference) => match &*reference.elem {
+            Type::Slice(_) | Type::TraitObject(_) => Err(unsupported()),
+            Type::Path(path) if path.path.is_ident("str") => Err(unsupported()),
+            elem => Ok(pointer(c_type(elem)?, reference.mutability.is_some())),
+        },
+        Type::Path(path) if path.qself.is_none() => {
+            let last = path.path.segments.last().unwrap();
+            let name = last.ident.to_string();
+            match (name.as_str(), &last.arguments) {
+                ("char" | "i128" | "u128" | "str", _) => Err(unsupported()),
+                (_, PathArguments::None) => {
+                    Ok(primitive(&name).map_or(name.clone(), str::to_string))
+                }
+                ("NonNull", arguments) => {
+                    let elem = type_arg(arguments).ok_or_else(unsupported)?;
+                    Ok(pointer(c_type(elem)?, true))
+                }
+                // Only `Option`s of non-null pointers are FFI-safe
+                ("Option", arguments) => match type_arg(arguments) {
+                    Some(inner @ Type::Reference(_)) => c_type(inner),
+                    Some(inner @ Type::Path(path))
+                        if path.path.segments.last().unwrap().ident == "NonNull" =>
+                    {
+                        c_type(inner)
+                    }
+                    _ => Err(unsupported()),
+                },
+                _ => Err(unsupported()),
+            }
+        }
+        _ => Err(unsupported()),
+    }
+}
+
+/// The C spelling of `ty`, with `Self` stored in `repr`. Errors are
+/// reported at `method` if `ty` has no span of its own.
+pub fn c_self_type(ty: &MaybeSelf, repr: &str, method: &Ident) -> Result<String> {
+    let self_kind = |kind: &SelfKind| match kind {
+        SelfKind::Value => Some(repr.to_string()),
+        SelfKind::Ptr { mutability, .. } | SelfKind::Ref { mutability, .. } => {
+            Some(pointer(repr.to_string(), mutability.is_some()))
+        }
+        SelfKind::Smart(_) | SelfKind::Pin(..) => None,
+    };
+    let ty = match ty {
+        MaybeSelf::Typed(ty) => return c_type(ty),
+        MaybeSelf::Self_(kind) => self_kind(kind),
+        MaybeSelf::Option(inner) => match &**inner {
+            MaybeSelf::Self_(kind @ SelfKind::Ref { .. }) => self_kind(kind),
+            _ => None,
+        },
+        _ => None,
+    };
+    ty.ok_or_else(|| Error::new_spanned(method, UNSUPPORTED))
+}
+
+/// The C declaration of the storage of an implementation, named `name`.
+pub fn repr_typedef(name: &str, layout: &Layout) -> String {
+    if layout.zero_sized {
+        // Never passed by value, so it may stay incomplete
+        return format!("typedef struct {name} {name};");
+    }
+    let storage = match &layout.size {
+        // `Repr` and `NonNullRepr` are two pointers aligned to at least 8
+        None if layout.align.is_none() => "_Alignas(8) void *words[2];".to_string(),
+        size => {
+            let size = match size {
+                Some(Expr::Lit(lit)) => match &lit.lit {
+                    Lit::Int(int) => int.base10_digits().to_string(),
+                    _ => unreachable!("`size` was checked to be an integer literal"),
+                },
+                None => "2 * sizeof(void *)".to_string(),
+                Some(_) => unreachable!("`size` was checked to be an integer literal"),
+            };
+            let align = layout
+                .align
+                .as_ref()
+                .map_or("8", |align| align.base10_digits());
+            format!("_Alignas({align}) unsigned char bytes[{size}];")
+        }
+    };
+    format!("typedef struct {name} {{ {storage} }} {name};")
+}
+
+/// A C header declaring the VTable of a trait, for implementations in C.
+pub struct CHeader {
+    pub trait_name: String,
+    pub package_version: String,
+    pub symbol: String,
+    pub layout_hash: u64,
+    pub repr: String,
+    /// Declarations of the VTable fields.
+    pub fields: Vec<String>,
+}
+
+impl CHeader {
+    /// Declaration of a field holding a pointer to a function.
+    pub fn fn_field(name: &str, inputs: &[String], output: &str) -> String {
+        let inputs = match inputs {
+            [] => "void".to_string(),
+            inputs => inputs.join(", "),
+        };
+        format!("{output} (*{})({inputs})", c_ident(name))
+    }
+
+    pub fn render(&self) -> String {
+        let CHeader {
+            trait_name,
+            package_version,
+            symbol,
+            layout_hash,
+            repr,
+            fields,
+        } = self;
+        let upper = trait_name.to_uppercase();
+
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            "\
+/* The VTable of the #[extern_trait] trait `{trait_name}` of {package_version}.
+ * Generated by #[extern_trait]; do not edit. */
+#ifndef EXTERN_TRAIT_{upper}_H
+#define EXTERN_TRAIT_{upper}_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#define {upper}_LAYOUT_HASH UINT64_C(0x{layout_hash:016x})
+#define {upper}_LAYOUT_VERSION \"{package_version}\"
+#define {upper}_VTABLE_SYMBOL \"{symbol}\"
+
+/* An implementation, stored in place of the proxy */
+{repr}
+
+typedef struct {trait_name}_VTable {{
+"
+        );
+        for field in fields {
+            let _ = writeln!(out, "    {field};");
+        }
+        let _ = write!(
+            out,
+            "\
+}} {trait_name}_VTable;
+
+#ifndef EXTERN_TRAIT_STR
+#define EXTERN_TRAIT_STR_(x) #x
+#define EXTERN_TRAIT_STR(x) EXTERN_TRAIT_STR_(x)
+#endif
+
+/* The VTable to define, with `layout_hash` set to {upper}_LAYOUT_HASH and
+ * `layout_version` to {upper}_LAYOUT_VERSION. The symbol is quoted, as
+ * derived symbols contain `$` and `-`. */
+extern {trait_name}_VTable const {trait_name}_vtable __asm__(
+    \"\\\"\" EXTERN_TRAIT_STR(__USER_LABEL_PREFIX__) {upper}_VTABLE_SYMBOL \"\\\"\");
+
+#endif
+"
+        );
+        out
+    }
+}
@@ -1,19 +1,33 @@
+mod c_abi;
+mod opaque;
+mod section;
 mod supertraits;
 mod symbol;
 mod types;
 
 use proc_macro2::TokenStream;
-use quote::{format_ident, quote};
-use syn::{Error, Ident, ItemTrait, Path, Result, ReturnType, TraitItem, Type, parse_quote};
+use quote::{format_ident, quote, quote_spanned};
+use syn::{
+    AngleBracketedGenericArguments, Error, GenericArgument, GenericParam, Generics, Ident,
+    ItemTrait, Lifetime, Path, PathArguments, Result, ReturnType, Token, TraitBoundModifier,
+    TraitItem, TraitItemConst, TraitItemFn, TraitItemType, Type, TypeParamBound, Visibility,
+    parse_quote, punctuated::Punctuated, spanned::Spanned, visit_mut::VisitMut,
+};
 
 use self::{
+    c_abi::{CHeader, c_self_type, c_type, repr_typedef},
+    opaque::OpaqueInfo,
+    section::Section,
     supertraits::{SupertraitInfo, collect_supertraits},
     symbol::Symbol,
-    types::VerifiedSignature,
+    types::{
+        FutureKind, SelfTypes, Substitute, TypeExt, VerifiedSignature, with_static_lifetimes,
+        with_type_param,
+    },
 };
 use crate::{
-    args::{Proxy, TraitArgs},
-    decl::types::{MaybeSelf, arg_names, make_return_type},
+    args::{Layout, Proxy, TraitArgs},
+    decl::types::{MaybeSelf, arg_names, make_return_type, mentions_self},
 };
 
 // ---------------------------------------------------------------------------
@@ -24,21 +38,284 @@ struct MethodInfo {
     sig: VerifiedSignature,
     /// `None` for trait's own methods, `Some(path)` for supertrait methods.
     supertrait_path: Option<Path>,
+    /// `Some(ident)` for methods of a bound on the associated type `Self::ident`.
+    assoc: Option<Ident>,
+    /// `Some((index, ty))` for one instance of a generic method.
+    instance: Option<(usize, Type)>,
 }
 
 impl MethodInfo {
-    /// VTable field name: `method` for own methods, `__Trait_method` for supertrait.
+    /// VTable field name: `method` for own methods, `__method_N` for the Nth instance
+    /// of a generic method, `__Trait_method` for supertrait, `__Assoc_Trait_method`
+    /// for associated type bounds.
     fn field_name(&self) -> Ident {
-        match &self.supertrait_path {
-            None => self.sig.ident.clone(),
+        let name = match &self.supertrait_path {
+            None => match &self.instance {
+                None => return self.sig.ident.clone(),
+                Some((index, _)) => return format_ident!("__{}_{}", self.sig.ident, index),
+            },
             Some(path) => {
                 let last = path.segments.last().unwrap();
-                format_ident!("__{}_{}", last.ident, self.sig.ident)
+                format!("{}_{}", last.ident, self.sig.ident)
             }
+        };
+        match &self.assoc {
+            None => format_ident!("__{}", name),
+            Some(assoc) => format_ident!("__{}_{}", assoc, name),
+        }
+    }
+
+    /// Name of the function exported for this method by a `direct` trait.
+    fn export_ident(&self) -> Ident {
+        format_ident!("__extern_{}", self.field_name())
+    }
+
+    /// Types substituted for `Self` in this method's signature.
+    fn self_types(&self, types: &SelfTypes) -> SelfTypes {
+        match &self.assoc {
+            None => types.clone(),
+            Some(assoc) => SelfTypes::new(types.assoc(assoc)),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// GenericInfo: generic methods, instantiated with the types in #[instances]
+// ---------------------------------------------------------------------------
+
+struct GenericInfo {
+    /// Signature with the type parameter left in place.
+    sig: VerifiedSignature,
+    /// Generics as emitted on the trait, bounded by `marker`.
+    generics: Generics,
+    param: Ident,
+    instances: Vec<Type>,
+    /// Hidden `unsafe` trait implemented for exactly the listed types.
+    marker: Ident,
+}
+
+impl GenericInfo {
+    /// Take the `#[instances(...)]` attribute off `item`, bounding its type
+    /// parameter by the generated marker trait.
+    fn take(
+        item: &mut TraitItemFn,
+        trait_ident: &Ident,
+        future: &Option<FutureKind>,
+    ) -> Result<Option<Self>> {
+        let Some(pos) = item
+            .attrs
+            .iter()
+            .position(|attr| attr.path().is_ident("instances"))
+        else {
+            return Ok(None);
+        };
+        let attr = item.attrs.remove(pos);
+        let instances: Vec<Type> = attr
+            .parse_args_with(Punctuated::<Type, Token![,]>::parse_terminated)?
+            .into_iter()
+            .collect();
+        if instances.is_empty() {
+            return Err(Error::new_spanned(
+                attr,
+                "#[instances] must list at least one type",
+            ));
+        }
+
+        let sig = &mut item.sig;
+        let marker = format_ident!("__{}_{}_Instance", trait_ident, sig.ident);
+        let mut params = sig
+            .generics
+            .params
+            .iter_mut()
+            .filter(|param| !matches!(param, GenericParam::Lifetime(_)));
+        let param = match (params.next(), params.next()) {
+            (Some(GenericParam::Type(param)), None) => {
+                param.bounds.push(parse_quote!(#marker));
+                param.ident.clone()
+            }
+            _ => {
+                return Err(Error::new_spanned(
+                    &sig.generics,
+                    "#[instances] methods must have exactly one type parameter",
+                ));
+            }
+        };
+
+        let mut erased = sig.clone();
+        erased.generics.params = std::mem::take(&mut erased.generics.params)
+            .into_iter()
+            .filter(|param| matches!(param, GenericParam::Lifetime(_)))
+            .collect();
+
+        Ok(Some(Self {
+            sig: VerifiedSignature::try_new_with(&erased, future.clone(), None)?,
+            generics: sig.generics.clone(),
+            param,
+            instances,
+            marker,
+        }))
+    }
+
+    /// Emit the marker trait and its impls for the listed types.
+    fn emit_marker(&self, vis: &Visibility, trait_ident: &Ident) -> TokenStream {
+        let marker = &self.marker;
+        let doc = format!(
+            "Types `{}::{}` may be instantiated with.",
+            trait_ident, self.sig.ident
+        );
+        let message = format!(
+            "`{{Self}}` is not listed in #[instances(...)] of `{}::{}`",
+            trait_ident, self.sig.ident
+        );
+        let impls = self.instances.iter().enumerate().map(|(index, ty)| {
+            quote! {
+                unsafe impl #marker for #ty {
+                    const INDEX: usize = #index;
+                }
+            }
+        });
+
+        quote! {
+            #[doc = #doc]
+            #[doc(hidden)]
+            #[allow(non_camel_case_types)]
+            #[diagnostic::on_unimplemented(message = #message)]
+            #vis unsafe trait #marker {
+                const INDEX: usize;
+            }
+
+            #(#impls)*
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// AssocInfo: associated types, backed by their own opaque proxy
+// ---------------------------------------------------------------------------
+
+struct AssocInfo {
+    ident: Ident,
+    copy: bool,
+    supertraits: Vec<SupertraitInfo>,
+}
+
+impl AssocInfo {
+    fn try_new(item: &TraitItemType) -> Result<Self> {
+        if !item.generics.params.is_empty() || item.generics.where_clause.is_some() {
+            return Err(Error::new_spanned(
+                &item.generics,
+                "#[extern_trait] associated types may not have generics",
+            ));
         }
+        if let Some((eq, ty)) = &item.default {
+            return Err(Error::new_spanned(
+                quote!(#eq #ty),
+                "#[extern_trait] associated types may not have defaults",
+            ));
+        }
+        if let Some(bound) = item.bounds.iter().find(|bound| {
+            matches!(bound, TypeParamBound::Trait(t) if matches!(t.modifier, TraitBoundModifier::Maybe(_)))
+        }) {
+            return Err(Error::new_spanned(
+                bound,
+                "#[extern_trait] associated types must be `Sized`",
+            ));
+        }
+
+        let supertraits = collect_supertraits(&item.bounds);
+        Ok(Self {
+            ident: item.ident.clone(),
+            copy: supertraits.iter().any(|info| info.path.is_ident("Copy")),
+            supertraits,
+        })
+    }
+
+    /// VTable field name for the associated type's drop glue.
+    fn drop_field(&self) -> Ident {
+        format_ident!("__{}_drop", self.ident)
     }
 }
 
+// ---------------------------------------------------------------------------
+// ConstInfo: associated constants, stored as VTable data
+// ---------------------------------------------------------------------------
+
+struct ConstInfo {
+    ident: Ident,
+    ty: Type,
+}
+
+impl ConstInfo {
+    fn try_new(item: &TraitItemConst) -> Result<Self> {
+        if !item.generics.params.is_empty() || item.generics.where_clause.is_some() {
+            return Err(Error::new_spanned(
+                &item.generics,
+                "#[extern_trait] associated constants may not have generics",
+            ));
+        }
+        if item.ty.contains_self() {
+            return Err(Error::new_spanned(
+                &item.ty,
+                "#[extern_trait] associated constants may not mention `Self`",
+            ));
+        }
+
+        Ok(Self {
+            ident: item.ident.clone(),
+            ty: with_static_lifetimes(&item.ty),
+        })
+    }
+
+    /// Name of the proxy accessor, e.g. `page_size` for `PAGE_SIZE`.
+    fn accessor(&self) -> Ident {
+        format_ident!("{}", self.ident.to_string().to_lowercase())
+    }
+}
+
+/// Associated functions of the proxy, besides the trait's methods and the
+/// constant accessors.
+const PROXY_FNS: &[&str] = &[
+    "assert_type_is_impl",
+    "from_impl",
+    "into_impl",
+    "downcast_ref",
+    "downcast_mut",
+];
+
+/// Check that the accessor of every constant is a valid name of its own on
+/// the proxy, e.g. that `const LEN` does not meet `fn len` and that
+/// `const TYPE` does not give `fn type`.
+fn check_const_accessors(consts: &[ConstInfo], items: &[TraitItem]) -> Result<()> {
+    let methods: Vec<_> = items
+        .iter()
+        .filter_map(|item| match item {
+            TraitItem::Fn(f) => Some(f.sig.ident.to_string()),
+            _ => None,
+        })
+        .collect();
+    for (index, c) in consts.iter().enumerate() {
+        let accessor = c.accessor().to_string();
+        let clash = match consts[..index].iter().find(|c| c.accessor() == accessor) {
+            // Keywords do not parse as identifiers
+            _ if syn::parse_str::<Ident>(&accessor).is_err() => "is a keyword".to_string(),
+            Some(other) => format!("collides with the accessor of `{}`", other.ident),
+            None if methods.contains(&accessor) => format!("collides with the method `{accessor}`"),
+            None if PROXY_FNS.contains(&accessor.as_str()) => {
+                format!("collides with the proxy function `{accessor}`")
+            }
+            None => continue,
+        };
+        return Err(Error::new_spanned(
+            &c.ident,
+            format!(
+                "the #[extern_trait] accessor `{accessor}()` of `{}` {clash}; rename the constant",
+                c.ident
+            ),
+        ));
+    }
+    Ok(())
+}
+
 // ---------------------------------------------------------------------------
 // ExpandCtx
 // ---------------------------------------------------------------------------
@@ -48,19 +325,50 @@ struct ExpandCtx {
     extern_trait: Path,
     proxy: Proxy,
     default: Option<Type>,
+    layout: Layout,
+    service: bool,
+    direct: bool,
+    c_abi: bool,
     input: ItemTrait,
+    /// Generic arguments of this instantiation of a generic trait, e.g. `<u32>`.
+    instance: Option<AngleBracketedGenericArguments>,
     // parsed
     sym: Symbol,
+    /// Fingerprint of the VTable layout, checked by debug builds of proxies.
+    layout_hash: u64,
+    /// C header declaring the VTable of an `abi = "C"` trait.
+    c_header: String,
     copy: bool,
     supertraits: Vec<SupertraitInfo>,
+    assoc: Vec<AssocInfo>,
+    consts: Vec<ConstInfo>,
+    generics: Vec<GenericInfo>,
 }
 
 impl ExpandCtx {
     fn new(args: TraitArgs, input: ItemTrait) -> Result<Self> {
-        if !input.generics.params.is_empty() {
+        for param in &input.generics.params {
+            match param {
+                GenericParam::Lifetime(param) if param.bounds.is_empty() => {}
+                GenericParam::Lifetime(param) => {
+                    return Err(Error::new_spanned(
+                        param,
+                        "#[extern_trait] does not support lifetime bounds",
+                    ));
+                }
+                _ => {
+                    return Err(Error::new_spanned(
+                        &input.generics,
+                        "#[extern_trait] may not have generics; list the instantiations to \
+                         generate in `instances = [...]`",
+                    ));
+                }
+            }
+        }
+        if let Some(where_clause) = &input.generics.where_clause {
             return Err(Error::new_spanned(
-                input.generics,
-                "#[extern_trait] may not have generics",
+                where_clause,
+                "#[extern_trait] may not have a where clause",
             ));
         }
 
@@ -68,20 +376,161 @@ impl ExpandCtx {
             extern_trait,
             proxy,
             default,
+            instances: _,
+            layout,
+            service,
+            symbol,
+            direct,
+            c_abi,
         } = args;
-        let sym = Symbol::new(input.ident.to_string());
+        let sym = match symbol {
+            Some(symbol) => Symbol::Explicit(symbol),
+            None => Symbol::new(&input.ident, input.ident.to_string())?,
+        };
 
         Ok(Self {
             extern_trait,
             proxy,
             default,
+            layout,
+            service,
+            direct,
+            c_abi,
             input,
+            instance: None,
             sym,
+            layout_hash: 0,
+            c_header: String::new(),
             copy: false,
             supertraits: Vec::new(),
+            assoc: Vec::new(),
+            consts: Vec::new(),
+            generics: Vec::new(),
         })
     }
 
+    /// One context per instantiation of a generic trait, with its type
+    /// parameters replaced by the arguments listed in `instances = [...]`.
+    fn instantiate(args: TraitArgs, input: &ItemTrait) -> Result<Vec<Self>> {
+        let mut params = Vec::new();
+        for param in &input.generics.params {
+            match param {
+                GenericParam::Type(param) if param.default.is_none() => {
+                    params.push(param.ident.clone())
+                }
+                _ => {
+                    return Err(Error::new_spanned(
+                        param,
+                        "generic #[extern_trait] traits may only have type parameters without \
+                         defaults",
+                    ));
+                }
+            }
+        }
+        if params.is_empty() {
+            return Err(Error::new_spanned(
+                &args.instances[0],
+                "`instances = [...]` requires a generic trait",
+            ));
+        }
+        if let Some(where_clause) = &input.generics.where_clause {
+            return Err(Error::new_spanned(
+                where_clause,
+                "generic #[extern_trait] traits may not have a where clause",
+            ));
+        }
+
+        let mut ctxs = Vec::new();
+        for path in &args.instances {
+            let segment = path.segments.last().unwrap();
+            let types = match &segment.arguments {
+                PathArguments::AngleBracketed(generic_args)
+                    if path.segments.len() == 1 && segment.ident == input.ident =>
+                {
+                    generic_args
+                        .args
+                        .iter()
+                        .map(|arg| match arg {
+                            GenericArgument::Type(ty) => Some(ty.clone()),
+                            _ => None,
+                        })
+                        .collect::<Option<Vec<_>>>()
+                        .filter(|types| types.len() == params.len())
+                }
+                _ => None,
+            };
+            let Some(types) = types else {
+                return Err(Error::new_spanned(
+                    path,
+                    format!(
+                        "expected an instantiation of `{}` with {} type argument(s)",
+                        input.ident,
+                        params.len()
+                    ),
+                ));
+            };
+
+            let substitutions: Vec<_> = params.iter().cloned().zip(types.iter().cloned()).collect();
+            let mut instance = input.clone();
+            instance.generics = Generics::default();
+            Substitute(&substitutions).visit_item_trait_mut(&mut instance);
+
+            let mut ctx = Self::new(args.clone(), instance)?;
+            ctx.instance = Some(parse_quote!(<#(#types),*>));
+            // Spacing of the printed tokens is not guaranteed to be stable
+            let trait_name = ctx.trait_path().to_string().replace(' ', "");
+            ctx.sym = Symbol::new(&ctx.input.ident, trait_name)?;
+            ctxs.push(ctx);
+        }
+
+        Ok(ctxs)
+    }
+
+    /// Lifetime parameters of the trait.
+    fn lifetimes(&self) -> Vec<&Lifetime> {
+        self.input
+            .generics
+            .lifetimes()
+            .map(|param| &param.lifetime)
+            .collect()
+    }
+
+    /// The trait as used in an impl, e.g. `Codec<u32>` for an instantiation
+    /// or `Parser<'a>` for a trait with lifetime parameters.
+    fn trait_path(&self) -> TokenStream {
+        let trait_ident = &self.input.ident;
+        let lifetimes = self.lifetimes();
+        let instance = &self.instance;
+        if lifetimes.is_empty() {
+            quote!(#trait_ident #instance)
+        } else {
+            quote!(#trait_ident<#(#lifetimes),*>)
+        }
+    }
+
+    /// The trait as a bound on implementation types, e.g. `for<'a> Parser<'a>`.
+    fn trait_bound(&self) -> TokenStream {
+        let lifetimes = self.lifetimes();
+        let trait_path = self.trait_path();
+        if lifetimes.is_empty() {
+            trait_path
+        } else {
+            quote!(for<#(#lifetimes),*> #trait_path)
+        }
+    }
+
+    /// The trait as used in a qualified call, with its lifetimes inferred.
+    fn trait_call_path(&self) -> TokenStream {
+        let trait_ident = &self.input.ident;
+        let lifetimes = self.lifetimes();
+        if lifetimes.is_empty() {
+            self.trait_path()
+        } else {
+            let elided = lifetimes.iter().map(|_| quote!('_));
+            quote!(#trait_ident<#(#elided),*>)
+        }
+    }
+
     // -----------------------------------------------------------------------
     // Collect all methods
     // -----------------------------------------------------------------------
@@ -89,22 +538,113 @@ impl ExpandCtx {
     fn collect_methods(&mut self) -> Result<Vec<MethodInfo>> {
         let mut methods = Vec::new();
 
-        // Trait's own methods
-        for item in &self.input.items {
-            let TraitItem::Fn(f) = item else {
+        // Trait's own methods, associated types and constants
+        for item in &mut self.input.items {
+            let generic = self.instance.is_some() || !self.input.generics.params.is_empty();
+            if generic && !matches!(item, TraitItem::Fn(_)) {
                 return Err(Error::new_spanned(
                     item,
-                    "#[extern_trait] may only contain methods",
+                    "generic #[extern_trait] traits may only contain methods",
                 ));
-            };
-            methods.push(MethodInfo {
-                sig: VerifiedSignature::try_new(&f.sig)?,
-                supertrait_path: None,
-            });
+            }
+            match item {
+                TraitItem::Fn(f) if self.service && mentions_self(&f.sig) => {
+                    return Err(Error::new_spanned(
+                        &f.sig,
+                        "#[extern_trait(service)] methods may not take or return `Self`",
+                    ));
+                }
+                TraitItem::Type(t) if self.service => {
+                    return Err(Error::new_spanned(
+                        t,
+                        "#[extern_trait(service)] traits may not have associated types",
+                    ));
+                }
+                TraitItem::Type(t) if self.c_abi => {
+                    return Err(Error::new_spanned(
+                        t,
+                        "#[extern_trait(abi = \"C\")] traits may not have associated types",
+                    ));
+                }
+                TraitItem::Fn(f) => {
+                    let future = FutureKind::take(&mut f.attrs, &f.sig)?;
+                    let opaque = OpaqueInfo::take(&mut f.attrs, &f.sig)?;
+                    if self.c_abi && (future.is_some() || opaque.is_some()) {
+                        return Err(Error::new_spanned(
+                            &f.sig,
+                            "#[extern_trait(abi = \"C\")] methods may not be `async` or return \
+                             `impl Trait`",
+                        ));
+                    }
+                    match GenericInfo::take(f, &self.input.ident, &future)? {
+                        Some(_) if self.instance.is_some() => {
+                            return Err(Error::new_spanned(
+                                &f.sig,
+                                "generic #[extern_trait] traits may not have #[instances] methods",
+                            ));
+                        }
+                        Some(_) if opaque.is_some() => {
+                            return Err(Error::new_spanned(
+                                &f.sig,
+                                "#[instances] methods may not return `impl Trait`",
+                            ));
+                        }
+                        Some(info) if info.sig.has_dyn_inputs() => {
+                            return Err(Error::new_spanned(
+                                &f.sig,
+                                "#[instances] methods may not take `impl Trait` arguments",
+                            ));
+                        }
+                        None => methods.push(MethodInfo {
+                            sig: VerifiedSignature::try_new_with(&f.sig, future, opaque)?,
+                            supertrait_path: None,
+                            assoc: None,
+                            instance: None,
+                        }),
+                        Some(info) => {
+                            for (index, ty) in info.instances.iter().enumerate() {
+                                let sig = with_type_param(&f.sig, &info.param, ty);
+                                methods.push(MethodInfo {
+                                    sig: VerifiedSignature::try_new_with(
+                                        &sig,
+                                        future.clone(),
+                                        None,
+                                    )?,
+                                    supertrait_path: None,
+                                    assoc: None,
+                                    instance: Some((index, ty.clone())),
+                                });
+                            }
+                            self.generics.push(info);
+                        }
+                    }
+                }
+                TraitItem::Type(t) => self.assoc.push(AssocInfo::try_new(t)?),
+                TraitItem::Const(c) => self.consts.push(ConstInfo::try_new(c)?),
+                _ => {
+                    return Err(Error::new_spanned(
+                        item,
+                        "#[extern_trait] may only contain methods, associated types and constants",
+                    ));
+                }
+            }
         }
 
+        check_const_accessors(&self.consts, &self.input.items)?;
+
         // Supertrait methods
         self.supertraits = collect_supertraits(&self.input.supertraits);
+        if self.service
+            && let Some(info) = self
+                .supertraits
+                .iter()
+                .find(|info| !info.methods.is_empty())
+        {
+            return Err(Error::new_spanned(
+                &info.path,
+                "#[extern_trait(service)] traits may only have supertraits without methods",
+            ));
+        }
         for info in &self.supertraits {
             if info.path.is_ident("Copy") {
                 self.copy = true;
@@ -113,13 +653,92 @@ impl ExpandCtx {
                 methods.push(MethodInfo {
                     sig: sig.clone(),
                     supertrait_path: Some(info.path.clone()),
+                    assoc: None,
+                    instance: None,
                 });
             }
         }
 
+        // Associated type bound methods
+        for assoc in &self.assoc {
+            for info in &assoc.supertraits {
+                for sig in &info.methods {
+                    methods.push(MethodInfo {
+                        sig: sig.clone(),
+                        supertrait_path: Some(info.path.clone()),
+                        assoc: Some(assoc.ident.clone()),
+                        instance: None,
+                    });
+                }
+            }
+        }
+
+        // Builds that disagree on the VTable layout get different symbols
+        let types = self.proxy_types();
+        let mut vtable = self.emit_vtable_struct(&methods, &types);
+        if self.direct {
+            // The methods of a `direct` trait are not in its VTable, but
+            // still part of its layout.
+            vtable.extend(methods.iter().map(|m| {
+                let field_name = m.field_name();
+                let fn_type = self.method_fn_type(&m.sig, &m.self_types(&types));
+                quote! { direct #field_name: #fn_type }
+            }));
+        }
+        self.layout_hash = symbol::fingerprint(vtable);
+        self.sym.set_abi(self.layout_hash);
+
+        if self.c_abi {
+            self.c_header = self.render_c_header(&methods)?;
+        }
+
         Ok(methods)
     }
 
+    /// Render the C header of an `abi = "C"` trait, checking that every
+    /// method and constant has a C type.
+    fn render_c_header(&self, methods: &[MethodInfo]) -> Result<String> {
+        let trait_name = self.input.ident.to_string();
+        let repr = format!("{trait_name}_Repr");
+
+        let mut fields = vec![
+            "uint64_t layout_hash".to_string(),
+            "char const *layout_version".to_string(),
+            CHeader::fn_field("drop", &[format!("{repr} *")], "void"),
+        ];
+        for c in &self.consts {
+            fields.push(format!("{} {}", c_type(&c.ty)?, c.ident));
+        }
+        for m in methods {
+            let ident = &m.sig.ident;
+            let inputs = m
+                .sig
+                .inputs
+                .iter()
+                .map(|input| c_self_type(input, &repr, ident))
+                .collect::<Result<Vec<_>>>()?;
+            let output = match &m.sig.output {
+                None => "void".to_string(),
+                Some(output) => c_self_type(output, &repr, ident)?,
+            };
+            fields.push(CHeader::fn_field(
+                &m.field_name().to_string(),
+                &inputs,
+                &output,
+            ));
+        }
+
+        let header = CHeader {
+            repr: repr_typedef(&repr, &self.layout),
+            trait_name,
+            package_version: symbol::package_version(),
+            symbol: self.vtable_symbol(),
+            layout_hash: self.layout_hash,
+            fields,
+        };
+        Ok(header.render())
+    }
+
     // -----------------------------------------------------------------------
     // VTable struct generation
     // -----------------------------------------------------------------------
@@ -129,140 +748,684 @@ impl ExpandCtx {
     }
 
     fn vtable_symbol(&self) -> String {
-        format!("{:#?}", self.sym)
+        self.sym.to_string()
+    }
+
+    /// Symbol of the function exported for `method` by a `direct` trait.
+    fn method_symbol(&self, method: &MethodInfo) -> String {
+        format!("{}${}", self.sym, method.field_name())
     }
 
-    /// `extern_trait::Repr` as a syn `Type`.
+    /// Methods called through the VTable, which are none for a `direct`
+    /// trait.
+    fn vtable_methods<'a>(&self, methods: &'a [MethodInfo]) -> &'a [MethodInfo] {
+        if self.direct { &[] } else { methods }
+    }
+
+    /// The link section resolving the default implementation, if the trait
+    /// has one and the `section-defaults` feature is enabled. `nightly-weak`
+    /// takes precedence, as Cargo may enable both for different dependents.
+    fn default_section(&self) -> Option<Section> {
+        (self.default.is_some()
+            && cfg!(feature = "section-defaults")
+            && !cfg!(feature = "nightly-weak"))
+        .then(|| Section::new(self.sym.section_name()))
+    }
+
+    /// `extern_trait::Repr`, or the `SizedRepr` set by `size` and `align`,
+    /// as a syn `Type`.
     fn repr_type(&self) -> Type {
+        self.layout.repr_type(&self.extern_trait)
+    }
+
+    /// Opaque proxy type generated for an associated type.
+    fn assoc_proxy_ident(&self, assoc: &AssocInfo) -> Ident {
+        format_ident!("{}{}", self.proxy.ident, assoc.ident)
+    }
+
+    /// Proxy-side types: the proxy and its associated type proxies.
+    fn proxy_types(&self) -> SelfTypes {
+        let proxy_ident = &self.proxy.ident;
+        SelfTypes {
+            this: parse_quote!(#proxy_ident),
+            assoc: self
+                .assoc
+                .iter()
+                .map(|assoc| {
+                    let ident = self.assoc_proxy_ident(assoc);
+                    (assoc.ident.clone(), parse_quote!(#ident))
+                })
+                .collect(),
+            boxed: None,
+        }
+    }
+
+    /// Impl-side types: `self_type` and its associated types through `trait_path`.
+    fn impl_types(&self, self_type: &Type, trait_path: &TokenStream) -> SelfTypes {
+        SelfTypes {
+            this: self_type.clone(),
+            assoc: self
+                .assoc
+                .iter()
+                .map(|assoc| {
+                    let ident = &assoc.ident;
+                    (
+                        assoc.ident.clone(),
+                        Type::Verbatim(quote!(<#self_type as #trait_path>::#ident)),
+                    )
+                })
+                .collect(),
+            boxed: None,
+        }
+    }
+
+    /// Impl-side types of a `#[extern_trait(boxed)]` impl, which stores
+    /// `Box<self_type>` in the proxy.
+    fn boxed_impl_types(&self, self_type: &Type, trait_path: &TokenStream) -> SelfTypes {
         let extern_trait = &self.extern_trait;
-        parse_quote!(#extern_trait::Repr)
+        SelfTypes {
+            this: Type::Verbatim(quote!(#extern_trait::__private::Box<#self_type>)),
+            boxed: Some(self_type.clone()),
+            ..self.impl_types(self_type, trait_path)
+        }
     }
 
     /// Build a `ReturnType`, replacing by-value `Self` with `Repr`.
-    fn return_type(&self, output: &Option<MaybeSelf>, self_type: &Type) -> ReturnType {
-        if output.as_ref().is_some_and(|o| o.is_self_value()) {
-            let repr = self.repr_type();
-            make_return_type(output, &repr)
-        } else {
-            make_return_type(output, self_type)
+    fn return_type(&self, output: &Option<MaybeSelf>, types: &SelfTypes) -> ReturnType {
+        let repr = self.repr_type();
+        match output {
+            None => ReturnType::Default,
+            Some(output) => ReturnType::Type(parse_quote!(->), output.abi_type(types, &repr)),
         }
     }
 
     /// Build a fn pointer type for a VTable method field.
     ///
-    /// `self_type` is substituted for ref/ptr Self. By-value Self uses `Repr`.
-    fn method_fn_type(&self, sig: &VerifiedSignature, self_type: &Type) -> TokenStream {
+    /// `types` are substituted for ref/ptr Self. By-value Self uses `Repr`.
+    /// Lifetimes of the trait and the method are bound by the fn pointer.
+    fn method_fn_type(&self, sig: &VerifiedSignature, types: &SelfTypes) -> TokenStream {
+        let lifetimes = self.method_lifetimes(sig);
+        let binder = (!lifetimes.is_empty()).then(|| quote!(for<#(#lifetimes),*>));
+        let fn_type = self.unbound_fn_type(sig, types);
+
+        quote! { #binder #fn_type }
+    }
+
+    /// Lifetimes of the trait and the method, as bound by the fn pointer of
+    /// a VTable method field.
+    fn method_lifetimes(&self, sig: &VerifiedSignature) -> Vec<Lifetime> {
+        self.lifetimes()
+            .into_iter()
+            .cloned()
+            .chain(sig.vtable_lifetimes())
+            .collect()
+    }
+
+    /// Build a fn pointer type for a method with its lifetimes in scope.
+    fn unbound_fn_type(&self, sig: &VerifiedSignature, types: &SelfTypes) -> TokenStream {
+        let unsafety = &sig.unsafety;
+        let abi = self.fn_abi(sig);
+        let (arg_types, output) = self.abi_signature(sig, types);
+
+        quote! { #unsafety #abi fn(#(#arg_types),*) #output }
+    }
+
+    /// The ABI of a method across the VTable: as declared, or `extern "C"`
+    /// for an `abi = "C"` trait. `None` for the Rust ABI.
+    fn fn_abi(&self, sig: &VerifiedSignature) -> Option<TokenStream> {
+        match &sig.abi {
+            Some(abi) => Some(quote!(#abi)),
+            None => self.c_abi.then(|| quote!(extern "C")),
+        }
+    }
+
+    /// Argument and return types of a method as passed across the VTable.
+    fn abi_signature(&self, sig: &VerifiedSignature, types: &SelfTypes) -> (Vec<Type>, ReturnType) {
         let VerifiedSignature {
-            unsafety,
+            unsafety: _,
+            abi: _,
             ident: _,
-            inputs,
-            output,
+            lifetimes: _,
+            future,
+            opaque,
+            inputs: _,
+            output: _,
         } = sig;
 
         let repr = self.repr_type();
+        let output = &sig.vtable_output();
 
-        let arg_types: Vec<_> = inputs
+        let arg_types: Vec<_> = sig
+            .vtable_inputs()
             .iter()
-            .map(|input| {
-                if input.is_self_value() {
-                    Box::new(repr.clone())
-                } else {
-                    input.to_type(self_type)
-                }
-            })
+            .map(|input| *input.abi_type(types, &repr))
             .collect();
 
-        let output = self.return_type(output, self_type);
+        let output = match (future, opaque) {
+            (_, Some(opaque)) => {
+                let opaque = opaque.to_type();
+                parse_quote!(-> #opaque)
+            }
+            (None, None) => self.return_type(output, types),
+            (Some(future), None) => {
+                let output = match output {
+                    None => Box::new(parse_quote!(())),
+                    Some(output) => output.abi_type(types, &repr),
+                };
+                let future = future.to_type(&self.extern_trait, *output);
+                parse_quote!(-> #future)
+            }
+        };
 
-        quote! { #unsafety fn(#(#arg_types),*) #output }
+        (arg_types, output)
     }
 
     /// Emit a `#[repr(C)]` VTable struct definition.
     ///
-    /// `self_type` is the type substituted for ref/ptr Self and drop pointer.
+    /// `types` are substituted for ref/ptr Self and drop pointers.
     /// By-value Self always uses `Repr`.
-    fn emit_vtable_struct(&self, methods: &[MethodInfo], self_type: &Type) -> TokenStream {
+    fn emit_vtable_struct(&self, methods: &[MethodInfo], types: &SelfTypes) -> TokenStream {
         let extern_trait = &self.extern_trait;
         let vtable_ident = self.vtable_ident();
+        let self_type = &types.this;
+        let repr = self.repr_type();
+
+        let assoc_drops: Vec<_> = self
+            .assoc
+            .iter()
+            .map(|assoc| {
+                let field_name = assoc.drop_field();
+                let assoc_type = types.assoc(&assoc.ident);
+                quote! { #field_name: unsafe fn(*mut #assoc_type) }
+            })
+            .collect();
 
-        let method_fields: Vec<_> = methods
+        let const_fields: Vec<_> = self
+            .consts
+            .iter()
+            .map(|c| {
+                let ConstInfo { ident, ty } = c;
+                quote! { #ident: #ty }
+            })
+            .collect();
+
+        let method_fields: Vec<_> = self
+            .vtable_methods(methods)
             .iter()
             .map(|m| {
                 let field_name = m.field_name();
-                let fn_type = self.method_fn_type(&m.sig, self_type);
+                let fn_type = self.method_fn_type(&m.sig, &m.self_types(types));
                 quote! { #field_name: #fn_type }
             })
             .collect();
 
+        // Opaque structs of returned `impl Trait`s
+        let opaque_structs = methods
+            .iter()
+            .filter_map(|m| m.sig.opaque.as_ref())
+            .map(|opaque| opaque.emit_struct(&self.extern_trait));
+
+        // Only traits whose implementations may be boxed record how the
+        // proxy stores them.
+        let boxed_fields = self.boxable(methods).then(|| {
+            quote! {
+                // whether the proxy stores the implementation in a `Box`
+                __boxed: bool,
+                __from_impl: unsafe fn(*mut ()) -> #repr,
+                __into_impl: unsafe fn(#repr, *mut ()),
+            }
+        });
+
+        // A C VTable holds only what a C implementation can provide, and
+        // leads with the fields checked before the rest is read.
+        if self.c_abi {
+            return quote! {
+                #[repr(C)]
+                #[allow(non_snake_case)]
+                struct #vtable_ident {
+                    layout_hash: u64,
+                    layout_version: *const ::core::ffi::c_char,
+                    drop: unsafe extern "C" fn(*mut #self_type),
+                    #(#const_fields,)*
+                    #(#method_fields),*
+                }
+
+                unsafe impl Sync for #vtable_ident {}
+            };
+        }
+
         quote! {
+            #(#opaque_structs)*
+
             #[repr(C)]
             #[allow(non_snake_case)]
             struct #vtable_ident {
                 typeid: #extern_trait::__private::ConstTypeId,
                 drop: unsafe fn(*mut #self_type),
+                layout_hash: u64,
+                // package and version the implementation was built against
+                layout_version: &'static str,
+                #boxed_fields
+                #(#assoc_drops,)*
+                #(#const_fields,)*
                 #(#method_fields),*
             }
         }
     }
 
+    /// Emit compile-time checks that `ty` fits in `Repr`, and that it is
+    /// non-null if `Repr` must be.
+    ///
+    /// `name` is a `concat!`-compatible expression naming `ty` in diagnostics.
+    fn emit_layout_asserts(&self, ty: &Type, name: TokenStream) -> TokenStream {
+        let repr = self.repr_type();
+        let non_null = self.emit_non_null_assert(ty);
+        let zero_sized = self.zero_sized_msg();
+
+        quote! {
+            assert!(
+                ::core::mem::size_of::<#repr>() != 0 || ::core::mem::size_of::<#ty>() == 0,
+                concat!(#name, #zero_sized)
+            );
+            assert!(
+                ::core::mem::size_of::<#ty>() <= ::core::mem::size_of::<#repr>(),
+                concat!(#name, " is too large to be used with #[extern_trait]")
+            );
+            assert!(
+                ::core::mem::align_of::<#ty>() <= ::core::mem::align_of::<#repr>(),
+                concat!(#name, " requires stricter alignment than #[extern_trait] can provide")
+            );
+            #non_null
+        }
+    }
+
+    /// Why implementation types must be zero-sized, which services imply
+    /// as their implementation is never constructed.
+    fn zero_sized_msg(&self) -> &'static str {
+        match self.service {
+            true => {
+                " must be zero-sized to implement a service #[extern_trait], whose implementation \
+                 is never constructed"
+            }
+            false => " must be zero-sized to implement a zero_sized #[extern_trait]",
+        }
+    }
+
+    /// Emit a check that `ty` is a `NonNullPointer` if `Repr` must be
+    /// non-null, reported at `ty`.
+    fn emit_non_null_assert(&self, ty: &Type) -> Option<TokenStream> {
+        let extern_trait = &self.extern_trait;
+        self.layout.non_null.then(|| {
+            quote_spanned! {ty.span()=>
+                #extern_trait::__private::assert_non_null::<#ty>();
+            }
+        })
+    }
+
+    /// Emit layout checks for the associated types of `self_type`.
+    fn emit_assoc_layout_asserts(&self, types: &SelfTypes) -> TokenStream {
+        let self_type = &types.this;
+
+        types
+            .assoc
+            .iter()
+            .map(|(ident, ty)| {
+                self.emit_layout_asserts(
+                    ty,
+                    quote!(stringify!(#self_type), "::", stringify!(#ident)),
+                )
+            })
+            .collect()
+    }
+
+    /// Emit checks that every type used in a slice or smart pointer of `Self`
+    /// or `Self::Assoc` has exactly the layout of `Repr`, so slice strides and
+    /// allocation layouts agree on both sides.
+    fn emit_exact_layout_asserts(&self, methods: &[MethodInfo], types: &SelfTypes) -> TokenStream {
+        let repr = self.repr_type();
+        let self_type = &types.this;
+
+        let mut elems: Vec<(Type, TokenStream)> = Vec::new();
+        for method in methods {
+            let signature = method.sig.inputs.iter().chain(&method.sig.output);
+            for elem in signature.flat_map(MaybeSelf::exact_layout_elems) {
+                let assoc = method.assoc.as_ref().or(elem);
+                let entry = match assoc {
+                    None => (self_type.clone(), quote!(stringify!(#self_type))),
+                    Some(ident) => (
+                        types.assoc(ident),
+                        quote!(stringify!(#self_type), "::", stringify!(#ident)),
+                    ),
+                };
+                if !elems.iter().any(|(ty, _)| *ty == entry.0) {
+                    elems.push(entry);
+                }
+            }
+        }
+
+        elems
+            .iter()
+            .map(|(ty, name)| {
+                quote! {
+                    assert!(
+                        ::core::mem::size_of::<#ty>() == ::core::mem::size_of::<#repr>()
+                            && ::core::mem::align_of::<#ty>() == ::core::mem::align_of::<#repr>(),
+                        concat!(#name, " must have exactly the size and alignment of Repr to be used in a slice or smart pointer")
+                    );
+                }
+            })
+            .collect()
+    }
+
     // -----------------------------------------------------------------------
     // Proxy-side: extern static + trait/supertrait impls
     // -----------------------------------------------------------------------
 
+    /// Emit the opaque proxy structs for associated types.
+    fn emit_assoc_proxies(&self) -> TokenStream {
+        let repr = self.repr_type();
+        let vis = &self.proxy.vis;
+        let trait_ident = &self.input.ident;
+
+        self.assoc
+            .iter()
+            .map(|assoc| {
+                let ident = self.assoc_proxy_ident(assoc);
+                let doc = format!(
+                    "Opaque proxy for the associated type `{}::{}`.",
+                    trait_ident, assoc.ident
+                );
+                quote! {
+                    #[doc = #doc]
+                    #[repr(transparent)]
+                    #vis struct #ident(#repr);
+                }
+            })
+            .collect()
+    }
+
+    /// Emit the VTable import, reached through `VT`. Debug builds check once
+    /// with `__check_vt` that the implementation was built for the same
+    /// layout, which explicit symbols and mixed build systems cannot
+    /// guarantee.
+    ///
+    /// Only calls that may come before any proxy exists check, see
+    /// [`ExpandCtx::emit_entry_check`].
+    ///
+    /// With a default in a link section, the VTable is the highest-priority
+    /// candidate of the section, resolved on first use.
     fn emit_extern_vtable(&self) -> TokenStream {
+        let extern_trait = &self.extern_trait;
         let vtable_ident = self.vtable_ident();
         let vtable_symbol = self.vtable_symbol();
+        let layout_hash = self.layout_hash;
+        let layout_version = symbol::package_version();
+        let trait_name = self.trait_path().to_string().replace(' ', "");
+        let impl_version = match self.c_abi {
+            true => quote! {
+                match vt.layout_version.is_null() {
+                    true => "<unknown>",
+                    false => unsafe { ::core::ffi::CStr::from_ptr(vt.layout_version) }
+                        .to_str()
+                        .unwrap_or("<invalid>"),
+                }
+            },
+            false => quote!(vt.layout_version),
+        };
+
+        let import = match self.default_section() {
+            Some(section) => {
+                let bounds = section.emit_bounds(extern_trait);
+                quote! {
+                    #[inline(always)]
+                    fn __vt() -> &'static #vtable_ident {
+                        use ::core::sync::atomic::{AtomicPtr, Ordering};
+                        static RESOLVED: AtomicPtr<()> = AtomicPtr::new(::core::ptr::null_mut());
+                        let mut vt = RESOLVED.load(Ordering::Acquire);
+                        if vt.is_null() {
+                            let (start, stop) = #bounds;
+                            vt = unsafe { #extern_trait::__private::resolve(start, stop) }.cast_mut();
+                            RESOLVED.store(vt, Ordering::Release);
+                        }
+                        unsafe { &*vt.cast::<#vtable_ident>() }
+                    }
+                }
+            }
+            None => quote! {
+                unsafe extern "Rust" {
+                    #[link_name = #vtable_symbol]
+                    safe static __VT: #vtable_ident;
+                }
+
+                #[inline(always)]
+                fn __vt() -> &'static #vtable_ident {
+                    &__VT
+                }
+            },
+        };
 
         quote! {
-            unsafe extern "Rust" {
-                #[link_name = #vtable_symbol]
-                safe static VT: #vtable_ident;
+            #import
+
+            // Unused by `direct` services, which only call their methods
+            #[allow(dead_code)]
+            struct __VTableRef;
+
+            #[allow(dead_code)]
+            const VT: __VTableRef = __VTableRef;
+
+            impl ::core::ops::Deref for __VTableRef {
+                type Target = #vtable_ident;
+
+                #[inline(always)]
+                fn deref(&self) -> &#vtable_ident {
+                    __vt()
+                }
+            }
+
+            #[cfg(debug_assertions)]
+            static __CHECKED: ::core::sync::atomic::AtomicBool =
+                ::core::sync::atomic::AtomicBool::new(false);
+
+            /// Check the VTable layout, once.
+            #[inline(always)]
+            #[allow(dead_code)]
+            fn __check_vt() {
+                #[cfg(debug_assertions)]
+                if !__CHECKED.load(::core::sync::atomic::Ordering::Relaxed) {
+                    __check_layout();
+                }
+            }
+
+            // `layout_hash` and `layout_version` directly follow `typeid`
+            // and `drop`, or lead a C VTable, so they can be read whatever
+            // the rest of the layout.
+            #[cfg(debug_assertions)]
+            #[cold]
+            #[inline(never)]
+            fn __check_layout() {
+                let vt = __vt();
+                if vt.layout_hash != #layout_hash {
+                    #extern_trait::__private::layout_mismatch(
+                        #trait_name,
+                        (#layout_version, #layout_hash),
+                        (#impl_version, vt.layout_hash),
+                    );
+                }
+                __CHECKED.store(true, ::core::sync::atomic::Ordering::Relaxed);
             }
         }
     }
 
+    /// Emit the imports of the functions a `direct` trait exports, one per
+    /// method.
+    fn emit_method_imports(&self, methods: &[MethodInfo]) -> TokenStream {
+        if !self.direct {
+            return TokenStream::new();
+        }
+        let types = self.proxy_types();
+
+        let imports = methods.iter().map(|m| {
+            let ident = m.export_ident();
+            let symbol = self.method_symbol(m);
+            let lifetimes = self.method_lifetimes(&m.sig);
+            let (arg_types, output) = self.abi_signature(&m.sig, &m.self_types(&types));
+            let arg_names = (0..arg_types.len()).map(|i| format_ident!("_{}", i));
+            let safety = match m.sig.unsafety {
+                Some(unsafety) => quote!(#unsafety),
+                None => quote!(safe),
+            };
+            let abi = self.fn_abi(&m.sig).unwrap_or(quote!(extern "Rust"));
+            quote! {
+                #[allow(non_snake_case, improper_ctypes)]
+                unsafe #abi {
+                    #[link_name = #symbol]
+                    #safety fn #ident<#(#lifetimes),*>(#(#arg_names: #arg_types),*) #output;
+                }
+            }
+        });
+
+        imports.collect()
+    }
+
+    /// The imported function of `method` of a `direct` trait.
+    fn emit_direct_callee(&self, method: &MethodInfo) -> TokenStream {
+        let ident = method.export_ident();
+        let check = self.emit_entry_check(method);
+        quote! {{
+            #check
+            #ident
+        }}
+    }
+
+    /// The VTable entry of `method`.
+    fn emit_vtable_callee(&self, method: &MethodInfo) -> TokenStream {
+        let field_name = method.field_name();
+        match self.emit_entry_check(method) {
+            Some(check) => quote! {{
+                #check
+                VT.#field_name
+            }},
+            None => quote! { VT.#field_name },
+        }
+    }
+
+    /// Check the VTable layout in debug builds if `method` may be called
+    /// before any proxy exists. Methods taking a proxy, or a value of its
+    /// associated types, come after the call that created it.
+    fn emit_entry_check(&self, method: &MethodInfo) -> Option<TokenStream> {
+        let entry = !method.sig.inputs.iter().any(MaybeSelf::holds_value);
+        entry.then(|| quote!(__check_vt();))
+    }
+
     fn emit_trait_impl(&self, methods: &[MethodInfo]) -> TokenStream {
         let proxy_ident = &self.proxy.ident;
         let trait_ident = &self.input.ident;
+        let trait_path = self.trait_path();
+        let (impl_generics, ..) = self.input.generics.split_for_impl();
         let unsafety = self.input.unsafety;
 
+        let assoc_types: Vec<_> = self
+            .assoc
+            .iter()
+            .map(|assoc| {
+                let ident = &assoc.ident;
+                let proxy = self.assoc_proxy_ident(assoc);
+                quote! { type #ident = #proxy; }
+            })
+            .collect();
+
+        // Constants are only known at link time, so using them in a const
+        // context is a compile error pointing at the runtime accessor.
+        let consts: Vec<_> = self
+            .consts
+            .iter()
+            .map(|c| {
+                let ConstInfo { ident, ty } = c;
+                let msg = format!(
+                    "`{}::{}` is provided at link time and cannot be used in a const context; \
+                     read it at runtime with `{}::{}()`",
+                    trait_ident,
+                    ident,
+                    proxy_ident,
+                    c.accessor(),
+                );
+                quote! { const #ident: #ty = panic!(#msg); }
+            })
+            .collect();
+
         let impl_methods: Vec<_> = methods
             .iter()
-            .filter(|m| m.supertrait_path.is_none())
+            .filter(|m| m.supertrait_path.is_none() && m.instance.is_none())
             .map(|m| self.emit_method_body(m))
             .collect();
 
+        let generic_methods: Vec<_> = self
+            .generics
+            .iter()
+            .map(|info| self.emit_generic_method_body(info, methods))
+            .collect();
+
         quote! {
-            #unsafety impl #trait_ident for #proxy_ident {
+            #unsafety impl #impl_generics #trait_path for #proxy_ident {
+                #(#assoc_types)*
+                #(#consts)*
                 #(#impl_methods)*
+                #(#generic_methods)*
             }
         }
     }
 
-    fn emit_supertrait_impls(&self, methods: &[MethodInfo]) -> TokenStream {
+    /// Emit supertrait impls, skipping those already in `emitted` by another
+    /// instantiation of the same generic trait.
+    fn emit_supertrait_impls(
+        &self,
+        methods: &[MethodInfo],
+        emitted: &mut Vec<(Ident, Path)>,
+    ) -> TokenStream {
         let proxy_ident = &self.proxy.ident;
+        let owners = std::iter::once((None, proxy_ident.clone(), &self.supertraits)).chain(
+            self.assoc.iter().map(|assoc| {
+                (
+                    Some(&assoc.ident),
+                    self.assoc_proxy_ident(assoc),
+                    &assoc.supertraits,
+                )
+            }),
+        );
         let mut impls = TokenStream::new();
 
-        for info in &self.supertraits {
-            let SupertraitInfo {
-                is_unsafe,
-                path,
-                methods: _,
-            } = info;
-
-            let supertrait_methods: Vec<_> = methods
-                .iter()
-                .filter(|m| m.supertrait_path.as_ref().is_some_and(|p| p == path))
-                .map(|m| self.emit_method_body(m))
-                .collect();
-
-            let unsafety = is_unsafe.then(|| quote! { unsafe });
-
-            impls.extend(quote! {
-                #unsafety impl #path for #proxy_ident {
-                    #(#supertrait_methods)*
+        for (owner, owner_ident, supertraits) in owners {
+            for info in supertraits {
+                let SupertraitInfo {
+                    is_unsafe,
+                    path,
+                    methods: _,
+                } = info;
+
+                let key = (owner_ident.clone(), path.clone());
+                if emitted.contains(&key) {
+                    continue;
                 }
-            });
+                emitted.push(key);
+
+                let supertrait_methods: Vec<_> = methods
+                    .iter()
+                    .filter(|m| {
+                        m.assoc.as_ref() == owner
+                            && m.supertrait_path.as_ref().is_some_and(|p| p == path)
+                    })
+                    .map(|m| self.emit_method_body(m))
+                    .collect();
+
+                let unsafety = is_unsafe.then(|| quote! { unsafe });
+
+                impls.extend(quote! {
+                    #unsafety impl #path for #owner_ident {
+                        #(#supertrait_methods)*
+                    }
+                });
+            }
         }
 
         impls
@@ -270,50 +1433,140 @@ impl ExpandCtx {
 
     /// Generate a single method body that calls through the VTable.
     fn emit_method_body(&self, method: &MethodInfo) -> TokenStream {
-        let extern_trait = &self.extern_trait;
-        let proxy_ident = &self.proxy.ident;
-        let proxy_type: Type = parse_quote!(#proxy_ident);
+        let types = method.self_types(&self.proxy_types());
+        let lifetimes = &method.sig.lifetimes;
+
+        let callee = match self.direct {
+            true => self.emit_direct_callee(method),
+            false => self.emit_vtable_callee(method),
+        };
+
+        self.emit_forwarding_fn(
+            &method.sig,
+            &types,
+            &parse_quote!(<#(#lifetimes),*>),
+            callee,
+        )
+    }
+
+    /// Generate a generic method that picks the VTable entry of its instance.
+    fn emit_generic_method_body(&self, info: &GenericInfo, methods: &[MethodInfo]) -> TokenStream {
+        let types = self.proxy_types();
+        let GenericInfo {
+            sig,
+            generics,
+            param,
+            instances: _,
+            marker,
+        } = info;
+
+        let arms = methods
+            .iter()
+            .filter(|m| m.supertrait_path.is_none() && m.sig.ident == sig.ident)
+            .filter_map(|m| {
+                let (index, _) = m.instance.as_ref()?;
+                let callee = match self.direct {
+                    true => self.emit_direct_callee(m),
+                    false => self.emit_vtable_callee(m),
+                };
+                Some(quote! { #index => (#callee) as *const () })
+            });
+
+        // `marker` is only implemented for the listed types, and `INDEX`
+        // selects the field instantiated with `param` itself.
+        let fn_type = self.unbound_fn_type(sig, &types);
+        let callee = quote! {
+            unsafe {
+                ::core::mem::transmute::<*const (), #fn_type>(
+                    match <#param as #marker>::INDEX {
+                        #(#arms,)*
+                        _ => unreachable!(),
+                    }
+                )
+            }
+        };
+
+        self.emit_forwarding_fn(sig, &types, generics, callee)
+    }
+
+    /// Generate a proxy method that converts its arguments, calls `callee`
+    /// and converts the result back.
+    fn emit_forwarding_fn(
+        &self,
+        sig: &VerifiedSignature,
+        types: &SelfTypes,
+        generics: &Generics,
+        callee: TokenStream,
+    ) -> TokenStream {
+        let repr = self.repr_type();
 
         let VerifiedSignature {
             unsafety,
+            abi,
             ident,
+            lifetimes: _,
+            future,
+            opaque,
             inputs,
             output,
-        } = &method.sig;
+        } = sig;
 
         let arg_names: Vec<_> = arg_names(inputs);
-        let arg_types: Vec<_> = inputs
+        let arg_types: Vec<_> = inputs.iter().map(|input| input.to_type(types)).collect();
+        // `impl Trait` args are borrowed as `&mut dyn Trait`
+        let arg_muts: Vec<_> = inputs
             .iter()
-            .map(|input| input.to_type(&proxy_type))
+            .map(|input| matches!(input, MaybeSelf::Dyn(_)).then(|| quote!(mut)))
             .collect();
 
         // Convert by-value Self args: ProxyType → Repr (transparent transmute)
         let call_args: Vec<_> = inputs
             .iter()
             .zip(&arg_names)
-            .map(|(input, name)| {
-                if input.is_self_value() {
-                    quote!(unsafe { #extern_trait::Repr::from_value(#name) })
-                } else {
-                    quote!(#name)
-                }
-            })
+            .map(|(input, name)| input.lower(quote!(#name), &repr))
             .collect();
 
-        let field_name = method.field_name();
-        let body = quote! { (VT.#field_name)(#(#call_args),*) };
+        let body = quote! { (#callee)(#(#call_args),*) };
 
         // Wrap Repr result back to ProxyType if by-value Self return
-        let body = if output.as_ref().is_some_and(|o| o.is_self_value()) {
-            quote! { #proxy_ident(#body) }
-        } else {
-            body
+        let body = match output {
+            _ if opaque.is_some() => opaque.as_ref().unwrap().wrap_proxy(body),
+            // The future borrows the `impl Trait` args, so it must own them
+            _ if future.is_some() && sig.has_dyn_inputs() => {
+                let result = match output {
+                    Some(output) => output.lift(quote!(__future.await), types, &repr),
+                    None => quote!(__future.await),
+                };
+                quote! {
+                    async move {
+                        let __future = #body;
+                        #result
+                    }
+                }
+            }
+            Some(output) if future.is_some() && output.is_converted() => {
+                let result = output.lift(quote!(__future.await), types, &repr);
+                quote! {
+                    let __future = #body;
+                    async move { #result }
+                }
+            }
+            Some(output) if future.is_none() => output.lift(body, types, &repr),
+            _ => body,
         };
 
-        let output = make_return_type(output, &proxy_type);
+        let output = match (future, make_return_type(output, types)) {
+            (None, output) => output,
+            (Some(_), ReturnType::Default) => {
+                parse_quote!(-> impl ::core::future::Future<Output = ()>)
+            }
+            (Some(_), ReturnType::Type(_, ty)) => {
+                parse_quote!(-> impl ::core::future::Future<Output = #ty>)
+            }
+        };
 
         quote! {
-            #unsafety fn #ident(#(#arg_names: #arg_types),*) #output {
+            #unsafety #abi fn #ident #generics(#(#arg_muts #arg_names: #arg_types),*) #output {
                 #body
             }
         }
@@ -325,24 +1578,191 @@ impl ExpandCtx {
 
     fn emit_drop_impl(&self) -> TokenStream {
         let proxy_ident = &self.proxy.ident;
+        let mut impls = TokenStream::new();
+
+        if !self.copy {
+            impls.extend(quote! {
+                impl Drop for #proxy_ident {
+                    fn drop(&mut self) {
+                        unsafe { (VT.drop)(self) }
+                    }
+                }
+            });
+        }
+
+        for assoc in self.assoc.iter().filter(|assoc| !assoc.copy) {
+            let ident = self.assoc_proxy_ident(assoc);
+            let field_name = assoc.drop_field();
+            impls.extend(quote! {
+                impl Drop for #ident {
+                    fn drop(&mut self) {
+                        unsafe { (VT.#field_name)(self) }
+                    }
+                }
+            });
+        }
+
+        impls
+    }
+
+    // -----------------------------------------------------------------------
+    // Service functions
+    // -----------------------------------------------------------------------
+
+    /// Emit the trait's methods as associated functions of the service type,
+    /// so they can be called without the trait in scope.
+    fn emit_service_fns(&self, methods: &[MethodInfo]) -> TokenStream {
+        let proxy_ident = &self.proxy.ident;
+        let vis = &self.proxy.vis;
+
+        let docs = |ident: &Ident| {
+            self.input.items.iter().find_map(|item| match item {
+                TraitItem::Fn(f) if f.sig.ident == *ident => Some(
+                    f.attrs
+                        .iter()
+                        .filter(|attr| attr.path().is_ident("doc"))
+                        .collect::<Vec<_>>(),
+                ),
+                _ => None,
+            })
+        };
+
+        let fns = methods
+            .iter()
+            .filter(|m| m.supertrait_path.is_none() && m.instance.is_none())
+            .map(|m| (&m.sig.ident, self.emit_method_body(m)))
+            .chain(self.generics.iter().map(|info| {
+                (
+                    &info.sig.ident,
+                    self.emit_generic_method_body(info, methods),
+                )
+            }))
+            .map(|(ident, body)| {
+                let docs = docs(ident).into_iter().flatten();
+                quote! {
+                    #(#docs)*
+                    #vis #body
+                }
+            });
 
         quote! {
-            impl Drop for #proxy_ident {
-                fn drop(&mut self) {
-                    unsafe { (VT.drop)(self) }
+            impl #proxy_ident {
+                #(#fns)*
+            }
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // Associated constant accessors
+    // -----------------------------------------------------------------------
+
+    fn emit_const_accessors(&self) -> Option<TokenStream> {
+        if self.consts.is_empty() {
+            return None;
+        }
+
+        let proxy_ident = &self.proxy.ident;
+        let trait_ident = &self.input.ident;
+
+        let accessors = self.consts.iter().map(|c| {
+            let ConstInfo { ident, ty } = c;
+            let accessor = c.accessor();
+            let doc = format!(
+                "Returns `{}::{}` of the linked implementation type.",
+                trait_ident, ident
+            );
+            quote! {
+                #[doc = #doc]
+                pub fn #accessor() -> #ty {
+                    __check_vt();
+                    VT.#ident
                 }
             }
+        });
+
+        Some(quote! {
+            impl #proxy_ident {
+                #(#accessors)*
+            }
+        })
+    }
+
+    // -----------------------------------------------------------------------
+    // C ABI: FFI-safety checks and the generated header
+    // -----------------------------------------------------------------------
+
+    /// Emit the C header and layout hash of an `abi = "C"` trait as
+    /// constants of the proxy, and checks that the types of its methods and
+    /// constants other than `Self` are FFI-safe.
+    fn emit_c_abi_items(&self, methods: &[MethodInfo]) -> Option<TokenStream> {
+        if !self.c_abi {
+            return None;
         }
+
+        let proxy_ident = &self.proxy.ident;
+        let trait_ident = &self.input.ident;
+        let c_header = &self.c_header;
+        let layout_hash = self.layout_hash;
+        let header_doc = format!(
+            "C header declaring the VTable of `{}`, for implementations written in C.",
+            trait_ident
+        );
+
+        let typed = |ty: &MaybeSelf| match ty {
+            MaybeSelf::Typed(ty) => Some((**ty).clone()),
+            _ => None,
+        };
+
+        // `improper_ctypes_definitions` only checks `extern "C"` functions,
+        // so each method's types are checked by one that is never called.
+        let method_checks = methods.iter().map(|m| {
+            let ident = format_ident!("__check_{}", m.field_name());
+            let lifetimes = self.method_lifetimes(&m.sig);
+            let arg_types = m.sig.inputs.iter().filter_map(typed);
+            let output = m
+                .sig
+                .output
+                .as_ref()
+                .and_then(typed)
+                .map(|ty| quote!(-> #ty));
+            quote! {
+                extern "C" fn #ident<#(#lifetimes),*>(#(_: #arg_types),*) #output {
+                    unreachable!()
+                }
+            }
+        });
+        let const_types = self.consts.iter().map(|c| &c.ty);
+
+        Some(quote! {
+            #[deny(improper_ctypes_definitions)]
+            #[allow(dead_code, non_snake_case)]
+            const _: () = {
+                #(#method_checks)*
+
+                extern "C" fn __check_consts(#(_: #const_types),*) {}
+            };
+
+            impl #proxy_ident {
+                #[doc = #header_doc]
+                pub const C_HEADER: &'static str = #c_header;
+
+                /// Fingerprint of the VTable layout, to set as its
+                /// `layout_hash`.
+                pub const LAYOUT_HASH: u64 = #layout_hash;
+            }
+        })
     }
 
     // -----------------------------------------------------------------------
     // Cast methods (from_impl, into_impl, downcast_ref, downcast_mut)
     // -----------------------------------------------------------------------
 
-    fn emit_cast_impl(&self) -> TokenStream {
+    fn emit_cast_impl(&self, methods: &[MethodInfo]) -> TokenStream {
         let extern_trait = &self.extern_trait;
+        let repr = self.repr_type();
         let proxy_ident = &self.proxy.ident;
         let trait_ident = &self.input.ident;
+        let trait_path = self.trait_bound();
 
         let panic_doc = format!(
             "# Panics\nPanics if the type parameter `T` is not an implementation type for \
@@ -350,9 +1770,60 @@ impl ExpandCtx {
             trait_ident
         );
 
+        // Implementations that may be boxed are moved through the VTable
+        // and found behind its `__boxed` flag, while inline ones are still
+        // moved in place. Others are stored in the proxy itself.
+        let (from_impl, into_impl, downcast_ref, downcast_mut) = match self.boxable(methods) {
+            true => (
+                quote! {
+                    if #extern_trait::__private::fits::<T, #repr>() && !VT.__boxed {
+                        return Self(unsafe { #extern_trait::__private::store::<T, #repr>(value) });
+                    }
+                    let mut value = ::core::mem::ManuallyDrop::new(value);
+                    Self(unsafe { (VT.__from_impl)((&mut *value as *mut T).cast()) })
+                },
+                quote! {
+                    let repr = unsafe { <#repr>::from_value(self) };
+                    if #extern_trait::__private::fits::<T, #repr>() && !VT.__boxed {
+                        return unsafe { #extern_trait::__private::load::<#repr, T>(repr) };
+                    }
+                    let mut value = ::core::mem::MaybeUninit::<T>::uninit();
+                    unsafe {
+                        (VT.__into_impl)(repr, value.as_mut_ptr().cast());
+                        value.assume_init()
+                    }
+                },
+                quote! {
+                    unsafe {
+                        if VT.__boxed {
+                            &**ptr.cast::<*const T>()
+                        } else {
+                            &*ptr.cast::<T>()
+                        }
+                    }
+                },
+                quote! {
+                    unsafe {
+                        if VT.__boxed {
+                            &mut **ptr.cast::<*mut T>()
+                        } else {
+                            &mut *ptr.cast::<T>()
+                        }
+                    }
+                },
+            ),
+            false => (
+                quote!(Self(unsafe { <#repr>::from_value(value) })),
+                quote!(unsafe { <#repr>::from_value(self).into_value::<T>() }),
+                quote!(unsafe { &*ptr.cast::<T>() }),
+                quote!(unsafe { &mut *ptr.cast::<T>() }),
+            ),
+        };
+
         quote! {
             impl #proxy_ident {
-                fn assert_type_is_impl<T: #trait_ident>() {
+                fn assert_type_is_impl<T: #trait_path>() {
+                    __check_vt();
                     let typeid = #extern_trait::__private::ConstTypeId::of::<T>();
                     assert!(
                         typeid == VT.typeid,
@@ -364,34 +1835,32 @@ impl ExpandCtx {
 
                 /// Convert the proxy type from the implementation type.
                 #[doc = #panic_doc]
-                pub fn from_impl<T: #trait_ident>(value: T) -> Self {
+                pub fn from_impl<T: #trait_path>(value: T) -> Self {
                     Self::assert_type_is_impl::<T>();
-                    Self(unsafe { #extern_trait::Repr::from_value(value) })
+                    #from_impl
                 }
 
                 /// Convert the proxy type into the implementation type.
                 #[doc = #panic_doc]
-                pub fn into_impl<T: #trait_ident>(self) -> T {
+                pub fn into_impl<T: #trait_path>(self) -> T {
                     Self::assert_type_is_impl::<T>();
-                    unsafe {
-                        #extern_trait::Repr::into_value(
-                            #extern_trait::Repr::from_value(self)
-                        )
-                    }
+                    #into_impl
                 }
 
                 /// Returns a reference to the implementation type.
                 #[doc = #panic_doc]
-                pub fn downcast_ref<T: #trait_ident>(&self) -> &T {
+                pub fn downcast_ref<T: #trait_path>(&self) -> &T {
                     Self::assert_type_is_impl::<T>();
-                    unsafe { &*(self as *const Self as *const T) }
+                    let ptr = self as *const Self;
+                    #downcast_ref
                 }
 
                 /// Returns a mutable reference to the implementation type.
                 #[doc = #panic_doc]
-                pub fn downcast_mut<T: #trait_ident>(&mut self) -> &mut T {
+                pub fn downcast_mut<T: #trait_path>(&mut self) -> &mut T {
                     Self::assert_type_is_impl::<T>();
-                    unsafe { &mut *(self as *mut Self as *mut T) }
+                    let ptr = self as *mut Self;
+                    #downcast_mut
                 }
             }
         }
@@ -401,30 +1870,38 @@ impl ExpandCtx {
     // Impl-side: macro_rules with VTable struct + static init
     // -----------------------------------------------------------------------
 
-    fn emit_macro_rules(&self, methods: &[MethodInfo]) -> TokenStream {
+    /// Emit the `macro_rules!` invoked by `#[extern_trait]` impls, with one
+    /// arm per instantiation of a generic trait.
+    fn emit_macro_rules(&self, arms: &[TokenStream]) -> TokenStream {
         let trait_ident = &self.input.ident;
         let macro_ident = format_ident!("__extern_trait_{}", trait_ident);
         let vis = &self.input.vis;
 
-        let vtable_ident = self.vtable_ident();
-        let vtable_symbol = self.vtable_symbol();
+        let fallback = self.instance.as_ref().map(|_| {
+            let msg = format!(
+                " is not listed in `instances = [...]` of #[extern_trait] `{}`",
+                trait_ident
+            );
+            quote! {
+                ($(boxed)? [$($args:tt)*] {$($base:tt)*} $trait:path: $ty:ty) => {
+                    ::core::compile_error!(concat!("`", stringify!($trait), "`", #msg));
+                };
+            }
+        });
 
-        let placeholder: Type = Type::Verbatim(quote!($ty));
-        let vtable_struct = self.emit_vtable_struct(methods, &placeholder);
-        let vtable_init = self.emit_vtable_init(methods, &placeholder, quote!($trait));
+        // The storage of the proxy, which impls check their type against,
+        // and whether it is a service, which has its own error message.
+        let repr = self.repr_type();
+        let service = self.service;
 
         quote! {
             #[doc(hidden)]
             #[macro_export]
             macro_rules! #macro_ident {
-                ($trait:path: $ty:ty) => {
-                    const _: () = {
-                        #vtable_struct
-
-                        #[unsafe(export_name = #vtable_symbol)]
-                        static VT: #vtable_ident = #vtable_init;
-                    };
-                };
+                (@repr) => { #repr };
+                (@service) => { #service };
+                #(#arms)*
+                #fallback
             }
 
             #[doc(hidden)]
@@ -433,21 +1910,345 @@ impl ExpandCtx {
         }
     }
 
+    /// Emit the impl-side constructors of the opaque structs of returned
+    /// `impl Trait`s.
+    fn emit_opaque_ctors(&self, methods: &[MethodInfo]) -> TokenStream {
+        methods
+            .iter()
+            .filter_map(|m| m.sig.opaque.as_ref())
+            .map(|opaque| opaque.emit_ctor(&self.extern_trait))
+            .collect()
+    }
+
+    /// Emit the `macro_rules!` arms exporting the VTable for an impl type,
+    /// stored inline or, with the `alloc` feature, in a `Box`.
+    fn emit_macro_arm(&self, methods: &[MethodInfo]) -> TokenStream {
+        let placeholder: Type = Type::Verbatim(quote!($ty));
+        let trait_path = quote!($trait);
+
+        let types = self.impl_types(&placeholder, &trait_path);
+        let body = self.emit_impl_vtable(methods, &types, &trait_path);
+        let boxed = cfg!(feature = "alloc").then(|| {
+            let body = match self.unboxable(methods) {
+                Some(msg) => quote!(::core::compile_error!(#msg);),
+                None => {
+                    let types = self.boxed_impl_types(&placeholder, &trait_path);
+                    self.emit_impl_vtable(methods, &types, &trait_path)
+                }
+            };
+            quote! {
+                (boxed $trait:path: $ty:ty) => {
+                    #body
+                };
+            }
+        });
+
+        quote! {
+            ($trait:path: $ty:ty) => {
+                #body
+            };
+            #boxed
+        }
+    }
+
+    /// Whether implementations may be boxed, which the VTable and the casts
+    /// of the proxy then account for.
+    fn boxable(&self, methods: &[MethodInfo]) -> bool {
+        cfg!(feature = "alloc") && self.unboxable(methods).is_none()
+    }
+
+    /// Emit the VTables of an instantiation for an impl type, stored inline
+    /// and, with the `alloc` feature, in a `Box`.
+    ///
+    /// The instantiation is named by the path `$($base)*` of the trait, which
+    /// `#[extern_trait]` impls pass in braces.
+    fn emit_instance_vtables(&self, methods: &[MethodInfo]) -> (TokenStream, Option<TokenStream>) {
+        let placeholder: Type = Type::Verbatim(quote!($ty));
+        let instance = &self.instance;
+        let trait_path = quote!($($base)* #instance);
+
+        let types = self.impl_types(&placeholder, &trait_path);
+        let body = self.emit_impl_vtable(methods, &types, &trait_path);
+        let boxed = cfg!(feature = "alloc").then(|| match self.unboxable(methods) {
+            Some(msg) => quote!(::core::compile_error!(#msg);),
+            None => {
+                let types = self.boxed_impl_types(&placeholder, &trait_path);
+                self.emit_impl_vtable(methods, &types, &trait_path)
+            }
+        });
+        (body, boxed)
+    }
+
+    /// Emit the `macro_rules!` arms of the instantiations of a generic trait.
+    ///
+    /// Drop, the casts and the downcasts of the proxy go through the VTable
+    /// of the first instantiation, so every VTable must come from the same
+    /// impl type. The first instantiation's impl thus exports them all, and
+    /// the others are implemented without `#[extern_trait]`.
+    fn emit_instance_arms(
+        ctxs: &[Self],
+        vtables: Vec<(TokenStream, Option<TokenStream>)>,
+    ) -> Vec<TokenStream> {
+        let primary = &ctxs[0];
+        let primary_args = &primary.instance.as_ref().unwrap().args;
+        let (bodies, boxed_bodies): (Vec<_>, Vec<_>) = vtables.into_iter().unzip();
+
+        let boxed = cfg!(feature = "alloc").then(|| {
+            quote! {
+                (boxed [#primary_args] {$($base:tt)*} $trait:path: $ty:ty) => {
+                    #(#boxed_bodies)*
+                };
+            }
+        });
+        let mut arms = vec![quote! {
+            ([#primary_args] {$($base:tt)*} $trait:path: $ty:ty) => {
+                #(#bodies)*
+            };
+            #boxed
+        }];
+
+        // Spacing of the printed tokens is not guaranteed to be stable
+        let name = |ctx: &Self| ctx.trait_path().to_string().replace(' ', "");
+        for ctx in &ctxs[1..] {
+            let args = &ctx.instance.as_ref().unwrap().args;
+            let msg = format!(
+                "`{}` is exported by the #[extern_trait] impl of `{}`, the first of `instances = \
+                 [...]`, for the same type; implement it without #[extern_trait]",
+                name(ctx),
+                name(primary)
+            );
+            arms.push(quote! {
+                ($(boxed)? [#args] {$($base:tt)*} $trait:path: $ty:ty) => {
+                    ::core::compile_error!(#msg);
+                };
+            });
+        }
+        arms
+    }
+
+    /// Why `#[extern_trait(boxed)]` impls are not supported, if they are not.
+    fn unboxable(&self, methods: &[MethodInfo]) -> Option<String> {
+        let trait_ident = &self.input.ident;
+        if self.copy {
+            return Some(format!(
+                "#[extern_trait(boxed)] is not supported for `{}`, which is `Copy`",
+                trait_ident
+            ));
+        }
+        if self.service {
+            return Some(format!(
+                "#[extern_trait(boxed)] is not supported for `{}`, which is a service",
+                trait_ident
+            ));
+        }
+        if self.layout.zero_sized {
+            return Some(format!(
+                "#[extern_trait(boxed)] is not supported for `{}`, which is zero-sized",
+                trait_ident
+            ));
+        }
+        methods.iter().filter(|m| m.assoc.is_none()).find_map(|m| {
+            let reason = m
+                .sig
+                .inputs
+                .iter()
+                .find_map(|input| input.unboxable(false))
+                .or_else(|| m.sig.output.as_ref()?.unboxable(true))?;
+            Some(format!(
+                "#[extern_trait(boxed)] is not supported for `{}`, whose method `{}` {}",
+                trait_ident, m.sig.ident, reason
+            ))
+        })
+    }
+
+    /// Emit the impl-side VTable for `types`, implementing `trait_path`.
+    fn emit_impl_vtable(
+        &self,
+        methods: &[MethodInfo],
+        types: &SelfTypes,
+        trait_path: &TokenStream,
+    ) -> TokenStream {
+        let vtable_ident = self.vtable_ident();
+        let vtable_symbol = self.vtable_symbol();
+
+        let vtable_struct = self.emit_vtable_struct(methods, types);
+        let vtable_init = self.emit_vtable_init(methods, types, trait_path.clone());
+        let opaque_ctors = self.emit_opaque_ctors(methods);
+
+        // The size of the implementation is checked by the impl, against
+        // the storage named by the trait's macro
+        let non_null_assert = self.emit_non_null_assert(&types.this);
+        let assoc_asserts = self.emit_assoc_layout_asserts(types);
+        let exact_asserts = self.emit_exact_layout_asserts(methods, types);
+
+        let method_exports = self.emit_method_exports(methods, types, trait_path.clone());
+
+        // Implementations outrank the default
+        let candidate = self
+            .default_section()
+            .map(|section| section.emit_candidate(&self.extern_trait, quote!(VT), 1));
+
+        quote! {
+            const _: () = {
+                #non_null_assert
+                #assoc_asserts
+                #exact_asserts
+
+                #vtable_struct
+
+                #opaque_ctors
+
+                #[unsafe(export_name = #vtable_symbol)]
+                static VT: #vtable_ident = #vtable_init;
+
+                #candidate
+
+                #method_exports
+            };
+        }
+    }
+
+    /// Emit the functions a `direct` trait exports for `types`, one per
+    /// method.
+    fn emit_method_exports(
+        &self,
+        methods: &[MethodInfo],
+        types: &SelfTypes,
+        trait_path: TokenStream,
+    ) -> TokenStream {
+        if !self.direct {
+            return TokenStream::new();
+        }
+
+        methods
+            .iter()
+            .map(|m| {
+                let symbol = self.method_symbol(m);
+                let method_fn = self.emit_method_fn(m, types, &trait_path);
+                quote! {
+                    #[unsafe(export_name = #symbol)]
+                    #method_fn
+                }
+            })
+            .collect()
+    }
+
+    /// Emit a function calling the implementation of `method` like its
+    /// VTable field would, with the ABI of the VTable.
+    fn emit_method_fn(
+        &self,
+        method: &MethodInfo,
+        types: &SelfTypes,
+        trait_path: &TokenStream,
+    ) -> TokenStream {
+        let ident = method.export_ident();
+        let unsafety = &method.sig.unsafety;
+        let lifetimes = self.method_lifetimes(&method.sig);
+        let (arg_types, output) = self.abi_signature(&method.sig, &method.self_types(types));
+        let arg_names: Vec<_> = (0..arg_types.len())
+            .map(|i| format_ident!("_{}", i))
+            .collect();
+        let init = self.emit_vtable_field_init(method, types, trait_path);
+        let call = match unsafety {
+            Some(_) => quote! { unsafe { __method(#(#arg_names),*) } },
+            None => quote! { __method(#(#arg_names),*) },
+        };
+        // The proxy checks the types a C implementation sees, whereas these
+        // also take the implementation type and its storage.
+        let abi = self.fn_abi(&method.sig);
+        let lint = abi
+            .is_some()
+            .then(|| quote!(#[allow(improper_ctypes_definitions)]));
+
+        quote! {
+            #[allow(non_snake_case)]
+            #lint
+            #unsafety #abi fn #ident<#(#lifetimes),*>(#(#arg_names: #arg_types),*) #output {
+                let __method: #unsafety fn(#(#arg_types),*) #output = #init;
+                #call
+            }
+        }
+    }
+
     /// Generate the VTable static initializer expression.
     fn emit_vtable_init(
         &self,
         methods: &[MethodInfo],
-        self_type: &Type,
+        types: &SelfTypes,
         trait_path: TokenStream,
     ) -> TokenStream {
         let extern_trait = &self.extern_trait;
         let vtable_ident = self.vtable_ident();
+        let stored_type = &types.this;
+        let self_type = types.impl_type();
+        let repr = self.repr_type();
+        let layout_hash = self.layout_hash;
+        let layout_version = symbol::package_version();
 
-        let method_inits: Vec<_> = methods
+        if self.c_abi {
+            return self.emit_c_vtable_init(methods, types, trait_path);
+        }
+
+        let assoc_drops: Vec<_> = self
+            .assoc
+            .iter()
+            .map(|assoc| {
+                let field_name = assoc.drop_field();
+                let assoc_type = types.assoc(&assoc.ident);
+                quote! {
+                    #field_name: |this: *mut #assoc_type| unsafe { ::core::ptr::drop_in_place(this) }
+                }
+            })
+            .collect();
+
+        // Move the implementation in and out of the proxy for `from_impl`
+        // and `into_impl`, boxing and unboxing it if it is boxed.
+        let boxed_inits = self.boxable(methods).then(|| {
+            let boxed = types.boxed.is_some();
+            let (boxed_value, unboxed_value) = match types.boxed {
+                Some(_) => (
+                    quote!(#extern_trait::__private::Box::new(value)),
+                    quote!(*value),
+                ),
+                None => (quote!(value), quote!(value)),
+            };
+            quote! {
+                __boxed: #boxed,
+                __from_impl: |value: *mut ()| unsafe {
+                    let value = ::core::ptr::read(value.cast::<#self_type>());
+                    <#repr>::from_value(#boxed_value)
+                },
+                __into_impl: |repr: #repr, out: *mut ()| unsafe {
+                    let value = <#repr>::into_value::<#stored_type>(repr);
+                    ::core::ptr::write(out.cast::<#self_type>(), #unboxed_value)
+                },
+            }
+        });
+
+        let const_inits: Vec<_> = self
+            .consts
+            .iter()
+            .map(|c| {
+                let ident = &c.ident;
+                quote! { #ident: <#self_type as #trait_path>::#ident }
+            })
+            .collect();
+
+        // Closures only coerce to Rust ABI fn pointers, so `extern` methods
+        // are initialized with a shim.
+        let method_inits: Vec<_> = self
+            .vtable_methods(methods)
             .iter()
             .map(|m| {
                 let field_name = m.field_name();
-                let init = self.emit_vtable_field_init(m, self_type, &trait_path);
+                let init = match m.sig.abi {
+                    Some(_) => {
+                        let ident = m.export_ident();
+                        let method_fn = self.emit_method_fn(m, types, &trait_path);
+                        quote!({ #method_fn #ident })
+                    }
+                    None => self.emit_vtable_field_init(m, types, &trait_path),
+                };
                 quote! { #field_name: #init }
             })
             .collect();
@@ -455,185 +2256,407 @@ impl ExpandCtx {
         quote! {
             #vtable_ident {
                 typeid: #extern_trait::__private::ConstTypeId::of::<#self_type>(),
-                drop: |this: *mut #self_type| unsafe { ::core::ptr::drop_in_place(this) },
+                drop: |this: *mut #stored_type| unsafe { ::core::ptr::drop_in_place(this) },
+                layout_hash: #layout_hash,
+                layout_version: #layout_version,
+                #boxed_inits
+                #(#assoc_drops,)*
+                #(#const_inits,)*
                 #(#method_inits),*
             }
         }
     }
 
+    /// Generate the initializer of a C VTable, whose fields point to
+    /// `extern "C"` functions calling the implementation.
+    fn emit_c_vtable_init(
+        &self,
+        methods: &[MethodInfo],
+        types: &SelfTypes,
+        trait_path: TokenStream,
+    ) -> TokenStream {
+        let vtable_ident = self.vtable_ident();
+        let stored_type = &types.this;
+        let self_type = types.impl_type();
+        let layout_hash = self.layout_hash;
+        let layout_version = symbol::package_version();
+
+        let const_inits: Vec<_> = self
+            .consts
+            .iter()
+            .map(|c| {
+                let ident = &c.ident;
+                quote! { #ident: <#self_type as #trait_path>::#ident }
+            })
+            .collect();
+
+        let method_fns: Vec<_> = methods
+            .iter()
+            .map(|m| self.emit_method_fn(m, types, &trait_path))
+            .collect();
+
+        let method_inits: Vec<_> = methods
+            .iter()
+            .map(|m| {
+                let field_name = m.field_name();
+                let ident = m.export_ident();
+                quote! { #field_name: #ident }
+            })
+            .collect();
+
+        quote! {{
+            unsafe extern "C" fn __drop(this: *mut #stored_type) {
+                unsafe { ::core::ptr::drop_in_place(this) }
+            }
+
+            #(#method_fns)*
+
+            #vtable_ident {
+                layout_hash: #layout_hash,
+                layout_version: concat!(#layout_version, "\0").as_ptr().cast(),
+                drop: __drop,
+                #(#const_inits,)*
+                #(#method_inits),*
+            }
+        }}
+    }
+
     /// Generate a single VTable field initializer closure for the impl side.
     fn emit_vtable_field_init(
         &self,
         method: &MethodInfo,
-        self_type: &Type,
+        types: &SelfTypes,
         trait_path: &TokenStream,
     ) -> TokenStream {
-        let extern_trait = &self.extern_trait;
-
         let MethodInfo {
             sig,
             supertrait_path,
+            assoc: _,
+            instance,
         } = method;
         let VerifiedSignature {
             unsafety,
+            abi: _,
             ident,
+            lifetimes,
+            future,
+            opaque,
             inputs,
             output,
         } = sig;
 
+        let types = method.self_types(types);
+        let self_type = types.impl_type();
         let repr = self.repr_type();
 
         // Parameter names: _0, _1, _2, ...
         let arg_names: Vec<_> = (0..inputs.len()).map(|i| format_ident!("_{}", i)).collect();
 
-        // Parameter types (same mapping as VTable struct fields)
-        let arg_types: Vec<_> = inputs
-            .iter()
-            .map(|input| {
-                if input.is_self_value() {
-                    Box::new(repr.clone())
-                } else {
-                    input.to_type(self_type)
-                }
-            })
-            .collect();
+        // Parameter types (same mapping as VTable struct fields). Named
+        // lifetimes cannot be declared on a closure, so its signature is
+        // inferred from the higher-ranked field type instead.
+        let params: Vec<_> =
+            if lifetimes.is_empty() && self.lifetimes().is_empty() && opaque.is_none() {
+                inputs
+                    .iter()
+                    .zip(&arg_names)
+                    .map(|(input, name)| {
+                        let ty = input.abi_type(&types, &repr);
+                        quote!(#name: #ty)
+                    })
+                    .collect()
+            } else {
+                arg_names.iter().map(|name| quote!(#name)).collect()
+            };
 
         // Convert arguments: by-value Self → Repr::into_value, otherwise pass through
         let call_args: Vec<_> = inputs
             .iter()
             .zip(&arg_names)
             .map(|(input, name)| {
-                if input.is_self_value() {
-                    quote!(unsafe { #extern_trait::Repr::into_value::<#self_type>(#name) })
-                } else {
-                    quote!(#name)
+                let arg = input.lift(quote!(#name), &types, &repr);
+                match types.boxed {
+                    Some(_) => input.unbox(arg),
+                    None => arg,
                 }
             })
             .collect();
 
+        // Convert the result: Self → Box<Self> for boxed impls, by-value Self → Repr
+        let lower = |output: &MaybeSelf, expr: TokenStream| {
+            let expr = match types.boxed {
+                Some(_) => output.rebox(expr, &self.extern_trait),
+                None => expr,
+            };
+            output.lower(expr, &repr)
+        };
+
         // Trait path for qualified call
         let trait_name = match &supertrait_path {
             None => trait_path.clone(),
             Some(path) => quote!(#path),
         };
 
+        let turbofish = instance.as_ref().map(|(_, ty)| quote!(::<#ty>));
+
         let body = quote! {
-            #unsafety { <#self_type as #trait_name>::#ident(#(#call_args),*) }
+            #unsafety { <#self_type as #trait_name>::#ident #turbofish(#(#call_args),*) }
         };
 
-        let body = if output.as_ref().is_some_and(|o| o.is_self_value()) {
-            quote! {
-                let __result = #body;
-                unsafe { #extern_trait::Repr::from_value(__result) }
+        let body = match (future, output) {
+            (None, Some(output)) if output.is_converted() => {
+                let result = lower(output, quote!(__result));
+                quote! {
+                    let __result = #body;
+                    #result
+                }
             }
-        } else {
-            body
+            (None, _) => body,
+            (Some(future), Some(output)) if output.is_converted() => {
+                let result = lower(output, quote!(__future.await));
+                let store = future.store(&self.extern_trait, quote!(async move { #result }));
+                quote! {
+                    let __future = #body;
+                    #store
+                }
+            }
+            (Some(future), _) => future.store(&self.extern_trait, body),
+        };
+        let body = match opaque {
+            Some(opaque) => opaque.store(body),
+            None => body,
         };
 
         quote! {
-            |#(#arg_names: #arg_types),*| {
+            |#(#params),*| {
                 #body
             }
         }
     }
 
     // -----------------------------------------------------------------------
-    // Default impl VTable with weak linkage
+    // Default impl VTable with weak linkage or in a link section
     // -----------------------------------------------------------------------
 
     fn emit_default_vtable(&self, methods: &[MethodInfo]) -> Option<TokenStream> {
-        let extern_trait = &self.extern_trait;
-
         let default_type = self.default.as_ref()?;
-        let trait_ident = &self.input.ident;
+        let trait_path = self.trait_call_path();
         let vtable_ident = self.vtable_ident();
         let vtable_symbol = self.vtable_symbol();
 
-        let vtable_struct = self.emit_vtable_struct(methods, default_type);
-        let vtable_init = self.emit_vtable_init(methods, default_type, quote!(#trait_ident));
+        let types = self.impl_types(default_type, &trait_path);
+        let vtable_struct = self.emit_vtable_struct(methods, &types);
+        let vtable_init = self.emit_vtable_init(methods, &types, trait_path);
+        let opaque_ctors = self.emit_opaque_ctors(methods);
 
-        Some(quote! {
-            const _: () = {
-                assert!(
-                    ::core::mem::size_of::<#default_type>() <= ::core::mem::size_of::<#extern_trait::Repr>(),
-                    concat!(stringify!(#default_type), " is too large to be used with #[extern_trait]")
-                );
-                assert!(
-                    ::core::mem::align_of::<#default_type>() <= ::core::mem::align_of::<#extern_trait::Repr>(),
-                    concat!(stringify!(#default_type), " requires stricter alignment than #[extern_trait] can provide")
-                );
+        let default_asserts =
+            self.emit_layout_asserts(default_type, quote!(stringify!(#default_type)));
+        let assoc_asserts = self.emit_assoc_layout_asserts(&types);
+        let exact_asserts = self.emit_exact_layout_asserts(methods, &types);
 
-                #vtable_struct
+        let default_vtable = match self.default_section() {
+            Some(section) => {
+                let candidate = section.emit_candidate(&self.extern_trait, quote!(DEFAULT_VT), 0);
+                quote! {
+                    static DEFAULT_VT: #vtable_ident = #vtable_init;
 
+                    #candidate
+                }
+            }
+            None => quote! {
                 #[unsafe(export_name = #vtable_symbol)]
                 #[linkage = "weak"]
                 static DEFAULT_VT: #vtable_ident = #vtable_init;
+            },
+        };
+
+        Some(quote! {
+            const _: () = {
+                #default_asserts
+                #assoc_asserts
+                #exact_asserts
+
+                #vtable_struct
+
+                #opaque_ctors
+
+                #default_vtable
             };
         })
     }
 
+    /// Emit the VTable symbols of the proxy, one per instantiation, for
+    /// tools and tests to decode with `extern_trait::symbol::decode`.
+    fn emit_vtable_symbols(&self, symbols: &[String]) -> TokenStream {
+        let proxy_ident = &self.proxy.ident;
+        quote! {
+            impl #proxy_ident {
+                #[doc(hidden)]
+                pub const __VTABLE_SYMBOLS: &'static [&'static str] = &[#(#symbols),*];
+            }
+        }
+    }
+
     // -----------------------------------------------------------------------
     // Top-level expand
     // -----------------------------------------------------------------------
 
-    fn expand(&mut self) -> Result<TokenStream> {
-        let methods = self.collect_methods()?;
-
-        let input = &self.input;
-        let proxy = self.proxy.expand(&self.extern_trait);
-
+    /// Emit the proxy-side VTable import and impls. Items that do not depend
+    /// on the trait's generic arguments are only emitted for the `primary`
+    /// instantiation.
+    fn emit_proxy_side(
+        &self,
+        methods: &[MethodInfo],
+        primary: bool,
+        emitted: &mut Vec<(Ident, Path)>,
+    ) -> TokenStream {
         // Proxy-side vtable struct
-        let proxy_ident = &self.proxy.ident;
-        let proxy_type: Type = parse_quote!(#proxy_ident);
-        let vtable_struct = self.emit_vtable_struct(&methods, &proxy_type);
+        let vtable_struct = self.emit_vtable_struct(methods, &self.proxy_types());
 
         // Extern vtable declaration
         let extern_vtable = self.emit_extern_vtable();
 
+        // Method imports of `direct` traits
+        let method_imports = self.emit_method_imports(methods);
+
         // Trait impl
-        let trait_impl = self.emit_trait_impl(&methods);
+        let trait_impl = self.emit_trait_impl(methods);
 
         // Supertrait impls
-        let supertrait_impls = self.emit_supertrait_impls(&methods);
+        let supertrait_impls = self.emit_supertrait_impls(methods, emitted);
 
-        // Drop impl (skip for Copy types)
-        let drop_impl = (!self.copy).then(|| self.emit_drop_impl());
+        // Drop impls (skip for Copy types and services)
+        let drop_impl = (primary && !self.service).then(|| self.emit_drop_impl());
 
-        // Cast methods
-        let cast_impl = self.emit_cast_impl();
+        // Associated constant accessors
+        let const_accessors = self.emit_const_accessors();
 
-        // Default impl VTable
-        let default_vtable = self.emit_default_vtable(&methods);
+        // Cast methods (skip for services and C VTables, which have no type ID)
+        let cast_impl =
+            (primary && !self.service && !self.c_abi).then(|| self.emit_cast_impl(methods));
 
-        // macro_rules
-        let macro_rules = self.emit_macro_rules(&methods);
+        // FFI-safety checks and C header
+        let c_abi_items = self.emit_c_abi_items(methods);
 
-        Ok(quote! {
-            #input
+        // Associated functions of services
+        let service_fns = self.service.then(|| self.emit_service_fns(methods));
 
-            #proxy
+        // Forwarding impls of returned `impl Trait`s
+        let opaque_impls = methods
+            .iter()
+            .filter_map(|m| m.sig.opaque.as_ref())
+            .map(OpaqueInfo::emit_proxy_impls);
+
+        // Default impl VTable
+        let default_vtable = self.emit_default_vtable(methods);
 
+        quote! {
             const _: () = {
                 #vtable_struct
 
                 #extern_vtable
 
+                #method_imports
+
                 #trait_impl
 
                 #supertrait_impls
 
                 #drop_impl
 
+                #const_accessors
+
                 #cast_impl
 
+                #c_abi_items
+
+                #service_fns
+
+                #(#opaque_impls)*
+
                 #default_vtable
             };
+        }
+    }
+
+    fn expand(&mut self) -> Result<TokenStream> {
+        let methods = self.collect_methods()?;
+
+        let input = &self.input;
+        let proxy = if self.service {
+            self.proxy.expand_unit()
+        } else {
+            self.proxy.expand(&self.repr_type())
+        };
+        let assoc_proxies = self.emit_assoc_proxies();
+
+        // Instance markers of generic methods
+        let generic_markers: Vec<_> = self
+            .generics
+            .iter()
+            .map(|info| info.emit_marker(&input.vis, &input.ident))
+            .collect();
+
+        let proxy_side = self.emit_proxy_side(&methods, true, &mut Vec::new());
+        let vtable_symbols = self.emit_vtable_symbols(&[self.vtable_symbol()]);
+
+        // macro_rules
+        let macro_rules = self.emit_macro_rules(&[self.emit_macro_arm(&methods)]);
+
+        Ok(quote! {
+            #input
+
+            #proxy
+
+            #assoc_proxies
+
+            #(#generic_markers)*
+
+            #proxy_side
+
+            #vtable_symbols
 
             #macro_rules
         })
     }
 }
 
+/// Expand a generic trait once per instantiation in `instances = [...]`.
+fn expand_instances(args: TraitArgs, input: ItemTrait) -> Result<TokenStream> {
+    let mut ctxs = ExpandCtx::instantiate(args, &input)?;
+
+    let mut emitted = Vec::new();
+    let mut proxy_sides = Vec::new();
+    let mut vtables = Vec::new();
+    for (i, ctx) in ctxs.iter_mut().enumerate() {
+        let methods = ctx.collect_methods()?;
+        proxy_sides.push(ctx.emit_proxy_side(&methods, i == 0, &mut emitted));
+        vtables.push(ctx.emit_instance_vtables(&methods));
+    }
+
+    let primary = &ctxs[0];
+    let proxy = primary.proxy.expand(&primary.repr_type());
+    let symbols: Vec<_> = ctxs.iter().map(ExpandCtx::vtable_symbol).collect();
+    let vtable_symbols = primary.emit_vtable_symbols(&symbols);
+    let arms = ExpandCtx::emit_instance_arms(&ctxs, vtables);
+    let macro_rules = primary.emit_macro_rules(&arms);
+
+    Ok(quote! {
+        #input
+
+        #proxy
+
+        #(#proxy_sides)*
+
+        #vtable_symbols
+
+        #macro_rules
+    })
+}
+
 pub fn expand(args: TraitArgs, input: ItemTrait) -> Result<TokenStream> {
+    if !args.instances.is_empty() {
+        return expand_instances(args, input);
+    }
     ExpandCtx::new(args, input)?.expand()
 }
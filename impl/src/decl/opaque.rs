@@ -0,0 +1,473 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    Attribute, Error, Expr, GenericArgument, Ident, Lifetime, Path, PathArguments, Result,
+    ReturnType, Signature, Type, TypeParamBound, parse_quote, visit_mut::VisitMut,
+};
+
+use super::types::{RenameLifetimes, TypeExt};
+
+/// Lifetime parameter of the generated opaque struct.
+fn struct_lifetime() -> Lifetime {
+    parse_quote!('__a)
+}
+
+/// Collects named lifetimes other than `'static`.
+struct NamedLifetimes(Vec<Lifetime>);
+
+impl VisitMut for NamedLifetimes {
+    fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+        if lifetime.ident != "_" && lifetime.ident != "static" && !self.0.contains(lifetime) {
+            self.0.push(lifetime.clone());
+        }
+    }
+}
+
+/// `Fn` or `FnMut` bound of a returned closure.
+#[derive(Debug, Clone)]
+struct CallBound {
+    mutable: bool,
+    inputs: Vec<Type>,
+    output: ReturnType,
+}
+
+/// A returned `impl Trait`, stored inline in `size` bytes and forwarded
+/// through a second-level opaque proxy.
+#[derive(Debug, Clone)]
+pub struct OpaqueInfo {
+    /// The generated opaque struct.
+    ident: Ident,
+    /// Impl-side function storing a value in `ident`.
+    ctor: Ident,
+    size: Expr,
+    /// Lifetime of the signature the returned value may borrow from, if
+    /// named in its bounds or taken from the receiver; `'static` otherwise.
+    pub lifetime: Option<Lifetime>,
+    /// Whether `lifetime` names the elided lifetime of the receiver.
+    pub receiver: bool,
+    /// `Item` of a returned iterator.
+    item: Option<Type>,
+    double_ended: bool,
+    exact_size: bool,
+    call: Option<CallBound>,
+    debug: bool,
+    display: bool,
+    send: bool,
+    sync: bool,
+}
+
+impl OpaqueInfo {
+    /// Take the `#[opaque(...)]` attribute off a method returning `impl Trait`.
+    pub fn take(attrs: &mut Vec<Attribute>, sig: &Signature) -> Result<Option<Self>> {
+        let Some(pos) = attrs.iter().position(|attr| attr.path().is_ident("opaque")) else {
+            return Ok(None);
+        };
+        let attr = attrs.remove(pos);
+        let ReturnType::Type(_, ty) = &sig.output else {
+            return Err(Error::new_spanned(
+                attr,
+                "#[opaque] is only allowed on methods returning `impl Trait`",
+            ));
+        };
+        let Type::ImplTrait(impl_trait) = &**ty else {
+            return Err(Error::new_spanned(
+                attr,
+                "#[opaque] is only allowed on methods returning `impl Trait`",
+            ));
+        };
+        if let Some(asyncness) = &sig.asyncness {
+            return Err(Error::new_spanned(
+                asyncness,
+                "#[opaque] is not supported on async functions",
+            ));
+        }
+
+        let mut size = None;
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("size") {
+                size = Some(meta.value()?.parse::<Expr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `size = ...`"))
+            }
+        })?;
+        let Some(size) = size else {
+            return Err(Error::new_spanned(attr, "#[opaque] expects `size = ...`"));
+        };
+
+        let mut named = NamedLifetimes(Vec::new());
+        named.visit_type_mut(&mut ty.as_ref().clone());
+        let lifetime = match named.0.as_slice() {
+            [] => None,
+            [lifetime] => Some(lifetime.clone()),
+            [_, lifetime, ..] => {
+                return Err(Error::new_spanned(
+                    lifetime,
+                    "#[extern_trait] `impl Trait` return types may borrow from at most one \
+                     lifetime",
+                ));
+            }
+        };
+
+        let mut info = Self {
+            ident: format_ident!("__{}_Opaque", sig.ident),
+            ctor: format_ident!("__{}_opaque", sig.ident),
+            size,
+            lifetime,
+            receiver: false,
+            item: None,
+            double_ended: false,
+            exact_size: false,
+            call: None,
+            debug: false,
+            display: false,
+            send: false,
+            sync: false,
+        };
+
+        for bound in &impl_trait.bounds {
+            let trait_bound = match bound {
+                TypeParamBound::Lifetime(_) => continue,
+                TypeParamBound::Trait(t) if t.lifetimes.is_none() => t,
+                _ => return Err(unsupported(bound)),
+            };
+            let segment = trait_bound.path.segments.last().unwrap();
+            let name = segment.ident.to_string();
+            match (name.as_str(), &segment.arguments) {
+                ("Iterator" | "DoubleEndedIterator" | "ExactSizeIterator", arguments) => {
+                    info.double_ended |= name == "DoubleEndedIterator";
+                    info.exact_size |= name == "ExactSizeIterator";
+                    match arguments {
+                        PathArguments::None => {}
+                        PathArguments::AngleBracketed(args)
+                            if args.args.len() == 1 && info.item.is_none() =>
+                        {
+                            let GenericArgument::AssocType(assoc) = &args.args[0] else {
+                                return Err(unsupported(bound));
+                            };
+                            if assoc.ident != "Item" {
+                                return Err(unsupported(bound));
+                            }
+                            info.item = Some(info.rename(&assoc.ty, true));
+                        }
+                        _ => return Err(unsupported(bound)),
+                    }
+                }
+                ("Fn" | "FnMut", PathArguments::Parenthesized(args)) if info.call.is_none() => {
+                    let output = match &args.output {
+                        ReturnType::Default => ReturnType::Default,
+                        ReturnType::Type(arrow, ty) => {
+                            ReturnType::Type(*arrow, Box::new(info.rename(ty, false)))
+                        }
+                    };
+                    info.call = Some(CallBound {
+                        mutable: name == "FnMut",
+                        inputs: args
+                            .inputs
+                            .iter()
+                            .map(|ty| info.rename(ty, false))
+                            .collect(),
+                        output,
+                    });
+                }
+                ("Debug", PathArguments::None) => info.debug = true,
+                ("Display", PathArguments::None) => info.display = true,
+                ("Send", PathArguments::None) => info.send = true,
+                ("Sync", PathArguments::None) => info.sync = true,
+                _ => return Err(unsupported(bound)),
+            }
+        }
+
+        let iterator = info.double_ended || info.exact_size || info.item.is_some();
+        if iterator && info.item.is_none() {
+            return Err(Error::new_spanned(
+                impl_trait,
+                "#[extern_trait] `impl Iterator` return types must name their `Item`",
+            ));
+        }
+        if iterator && info.call.is_some() {
+            return Err(Error::new_spanned(
+                impl_trait,
+                "#[extern_trait] `impl Trait` return types may not be both an iterator and a \
+                 closure",
+            ));
+        }
+        if ty.contains_self()
+            || info.item.iter().any(TypeExt::contains_self)
+            || info.call.iter().any(|call| {
+                call.inputs.iter().any(TypeExt::contains_self)
+                    || matches!(&call.output, ReturnType::Type(_, ty) if ty.contains_self())
+            })
+        {
+            return Err(Error::new_spanned(
+                impl_trait,
+                "#[extern_trait] `impl Trait` return types may not mention `Self`",
+            ));
+        }
+
+        Ok(Some(info))
+    }
+
+    /// `ty` with the borrowed lifetime, and with `elided` also elided
+    /// lifetimes, named after the struct's lifetime parameter.
+    fn rename(&self, ty: &Type, elided: bool) -> Type {
+        let mut ty = ty.clone();
+        RenameLifetimes {
+            from: self.lifetime.as_ref(),
+            to: &struct_lifetime(),
+            elided,
+        }
+        .visit_type_mut(&mut ty);
+        ty
+    }
+
+    /// The generated opaque struct, as returned through the VTable.
+    pub fn to_type(&self) -> Type {
+        let ident = &self.ident;
+        let lifetime = match &self.lifetime {
+            Some(lifetime) => lifetime.clone(),
+            None => parse_quote!('static),
+        };
+        Type::Verbatim(quote!(#ident<#lifetime>))
+    }
+
+    /// Emit the `#[repr(C)]` opaque struct shared by both sides: the value in
+    /// `Storage`, followed by a function pointer per forwarded method.
+    pub fn emit_struct(&self, extern_trait: &Path) -> TokenStream {
+        let Self {
+            ident,
+            ctor: _,
+            size,
+            lifetime: _,
+            receiver: _,
+            item,
+            double_ended,
+            exact_size: _,
+            call,
+            debug,
+            display,
+            send: _,
+            sync: _,
+        } = self;
+        let a = struct_lifetime();
+
+        let mut fields = Vec::new();
+        if let Some(item) = item {
+            fields.push(quote! { next: unsafe fn(*mut ()) -> Option<#item> });
+            fields.push(quote! { size_hint: unsafe fn(*const ()) -> (usize, Option<usize>) });
+        }
+        if *double_ended {
+            fields.push(quote! { next_back: unsafe fn(*mut ()) -> Option<#item> });
+        }
+        if let Some(CallBound {
+            mutable,
+            inputs,
+            output,
+        }) = call
+        {
+            let (field, ptr) = match mutable {
+                false => (quote!(call), quote!(*const ())),
+                true => (quote!(call_mut), quote!(*mut ())),
+            };
+            fields.push(quote! { #field: unsafe fn(#ptr, #(#inputs),*) #output });
+        }
+        let fmt = quote! {
+            unsafe fn(*const (), &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result
+        };
+        if *debug {
+            fields.push(quote! { debug: #fmt });
+        }
+        if *display {
+            fields.push(quote! { display: #fmt });
+        }
+
+        quote! {
+            #[repr(C)]
+            #[allow(non_camel_case_types, dead_code)]
+            struct #ident<#a> {
+                storage: #extern_trait::__private::Storage<{ #size }>,
+                drop: unsafe fn(*mut ()),
+                #(#fields,)*
+                _marker: ::core::marker::PhantomData<(&#a (), *mut ())>,
+            }
+        }
+    }
+
+    /// Emit the proxy-side impls forwarding the bounds through the struct.
+    pub fn emit_proxy_impls(&self) -> TokenStream {
+        let ident = &self.ident;
+        let a = struct_lifetime();
+        let mut impls = quote! {
+            impl Drop for #ident<'_> {
+                fn drop(&mut self) {
+                    unsafe { (self.drop)(self.storage.as_mut_ptr()) }
+                }
+            }
+        };
+
+        if let Some(item) = &self.item {
+            impls.extend(quote! {
+                impl<#a> Iterator for #ident<#a> {
+                    type Item = #item;
+
+                    fn next(&mut self) -> Option<Self::Item> {
+                        unsafe { (self.next)(self.storage.as_mut_ptr()) }
+                    }
+
+                    fn size_hint(&self) -> (usize, Option<usize>) {
+                        unsafe { (self.size_hint)(self.storage.as_ptr()) }
+                    }
+                }
+            });
+        }
+        if self.double_ended {
+            impls.extend(quote! {
+                impl DoubleEndedIterator for #ident<'_> {
+                    fn next_back(&mut self) -> Option<Self::Item> {
+                        unsafe { (self.next_back)(self.storage.as_mut_ptr()) }
+                    }
+                }
+            });
+        }
+        if self.exact_size {
+            impls.extend(quote! {
+                impl ExactSizeIterator for #ident<'_> {}
+            });
+        }
+        for (enabled, fmt_trait, field) in [
+            (self.debug, quote!(Debug), quote!(debug)),
+            (self.display, quote!(Display), quote!(display)),
+        ] {
+            if enabled {
+                impls.extend(quote! {
+                    impl ::core::fmt::#fmt_trait for #ident<'_> {
+                        fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                            unsafe { (self.#field)(self.storage.as_ptr(), f) }
+                        }
+                    }
+                });
+            }
+        }
+        if self.send {
+            impls.extend(quote! { unsafe impl Send for #ident<'_> {} });
+        }
+        if self.sync {
+            impls.extend(quote! { unsafe impl Sync for #ident<'_> {} });
+        }
+
+        impls
+    }
+
+    /// Impl-side expression storing the value returned by `expr`.
+    pub fn store(&self, expr: TokenStream) -> TokenStream {
+        let ctor = &self.ctor;
+        quote!(#ctor(#expr))
+    }
+
+    /// Wrap the struct returned by `expr` into the proxy's return value,
+    /// which for a closure is a closure calling through the struct.
+    pub fn wrap_proxy(&self, expr: TokenStream) -> TokenStream {
+        let Some(CallBound {
+            mutable, inputs, ..
+        }) = &self.call
+        else {
+            return expr;
+        };
+
+        let args: Vec<_> = (0..inputs.len())
+            .map(|i| format_ident!("__{}", i))
+            .collect();
+        if *mutable {
+            quote! {
+                let mut __opaque = #expr;
+                move |#(#args),*| unsafe {
+                    (__opaque.call_mut)(__opaque.storage.as_mut_ptr(), #(#args),*)
+                }
+            }
+        } else {
+            quote! {
+                let __opaque = #expr;
+                move |#(#args),*| unsafe { (__opaque.call)(__opaque.storage.as_ptr(), #(#args),*) }
+            }
+        }
+    }
+
+    /// Emit the impl-side constructor storing any value satisfying the bounds.
+    pub fn emit_ctor(&self, extern_trait: &Path) -> TokenStream {
+        let Self { ident, ctor, .. } = self;
+        let a = struct_lifetime();
+        let mut bounds = Vec::new();
+        let mut fields = Vec::new();
+
+        if let Some(item) = &self.item {
+            bounds.push(quote!(Iterator<Item = #item>));
+            fields.push(quote! { next: |__ptr| unsafe { (*__ptr.cast::<F>()).next() } });
+            fields.push(quote! { size_hint: |__ptr| unsafe { (*__ptr.cast::<F>()).size_hint() } });
+        }
+        if self.double_ended {
+            bounds.push(quote!(DoubleEndedIterator));
+            fields.push(quote! { next_back: |__ptr| unsafe { (*__ptr.cast::<F>()).next_back() } });
+        }
+        if self.exact_size {
+            bounds.push(quote!(ExactSizeIterator));
+        }
+        if let Some(CallBound {
+            mutable,
+            inputs,
+            output,
+        }) = &self.call
+        {
+            let args: Vec<_> = (0..inputs.len())
+                .map(|i| format_ident!("__{}", i))
+                .collect();
+            if *mutable {
+                bounds.push(quote!(FnMut(#(#inputs),*) #output));
+                fields.push(quote! {
+                    call_mut: |__ptr, #(#args),*| unsafe { (*__ptr.cast::<F>())(#(#args),*) }
+                });
+            } else {
+                bounds.push(quote!(Fn(#(#inputs),*) #output));
+                fields.push(quote! {
+                    call: |__ptr, #(#args),*| unsafe { (*__ptr.cast::<F>())(#(#args),*) }
+                });
+            }
+        }
+        for (enabled, fmt_trait, field) in [
+            (self.debug, quote!(Debug), quote!(debug)),
+            (self.display, quote!(Display), quote!(display)),
+        ] {
+            if enabled {
+                bounds.push(quote!(::core::fmt::#fmt_trait));
+                fields.push(quote! {
+                    #field: |__ptr, f| unsafe { ::core::fmt::#fmt_trait::fmt(&*__ptr.cast::<F>(), f) }
+                });
+            }
+        }
+        if self.send {
+            bounds.push(quote!(Send));
+        }
+        if self.sync {
+            bounds.push(quote!(Sync));
+        }
+
+        quote! {
+            fn #ctor<#a, F: #(#bounds +)* #a>(value: F) -> #ident<#a> {
+                #ident {
+                    storage: #extern_trait::__private::Storage::new(value),
+                    drop: |__ptr| unsafe { ::core::ptr::drop_in_place(__ptr.cast::<F>()) },
+                    #(#fields,)*
+                    _marker: ::core::marker::PhantomData,
+                }
+            }
+        }
+    }
+}
+
+fn unsupported(bound: &TypeParamBound) -> Error {
+    Error::new_spanned(
+        bound,
+        "#[extern_trait] `impl Trait` return types support `Iterator`, `DoubleEndedIterator`, \
+         `ExactSizeIterator`, `Fn`, `FnMut`, `Debug`, `Display`, `Send`, `Sync` and lifetime \
+         bounds",
+    )
+}
@@ -0,0 +1,122 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Path;
+
+/// Targets whose linker defines `__start_<section>` and `__stop_<section>`
+/// for sections named like C identifiers.
+fn elf() -> TokenStream {
+    quote! {
+        any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "fuchsia",
+            target_os = "illumos",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd",
+            target_os = "dragonfly",
+            target_os = "none",
+        )
+    }
+}
+
+/// Targets whose linker defines `section$start$<segment>$<section>` and
+/// `section$end$<segment>$<section>`.
+fn macho() -> TokenStream {
+    quote!(target_vendor = "apple")
+}
+
+/// Targets whose linker sorts `<section>$<suffix>` sections by suffix.
+fn coff() -> TokenStream {
+    quote!(target_os = "windows")
+}
+
+/// The link section collecting the candidate VTables of a trait with a
+/// default implementation, for the `section-defaults` feature.
+///
+/// Both the default and every `#[extern_trait] impl` add a candidate, and
+/// the proxy picks the one with the highest priority on first use. This
+/// gives the semantics of a weak default on stable Rust, as long as the
+/// linker collects the section.
+pub struct Section {
+    /// Section name, at most 16 characters for Mach-O.
+    name: String,
+}
+
+impl Section {
+    pub fn new(name: String) -> Self {
+        Self { name }
+    }
+
+    /// Emit a candidate `vtable` of `priority` into the section.
+    pub fn emit_candidate(
+        &self,
+        extern_trait: &Path,
+        vtable: TokenStream,
+        priority: usize,
+    ) -> TokenStream {
+        let (elf, macho, coff) = (elf(), macho(), coff());
+        let elf_section = &self.name;
+        let macho_section = format!("__DATA,{},regular,no_dead_strip", self.name);
+        let coff_section = format!(".{}$b", self.name);
+
+        quote! {
+            #[cfg_attr(#elf, unsafe(link_section = #elf_section))]
+            #[cfg_attr(#macho, unsafe(link_section = #macho_section))]
+            #[cfg_attr(#coff, unsafe(link_section = #coff_section))]
+            #[used]
+            static CANDIDATE: #extern_trait::__private::Candidate =
+                #extern_trait::__private::Candidate {
+                    vtable: &raw const #vtable as *const (),
+                    priority: #priority,
+                };
+        }
+    }
+
+    /// Emit an expression for the `(start, stop)` pointers of the section.
+    pub fn emit_bounds(&self, extern_trait: &Path) -> TokenStream {
+        let (elf, macho, coff) = (elf(), macho(), coff());
+        let elf_start = format!("__start_{}", self.name);
+        let elf_stop = format!("__stop_{}", self.name);
+        let macho_start = format!("\x01section$start$__DATA${}", self.name);
+        let macho_stop = format!("\x01section$end$__DATA${}", self.name);
+        let coff_start = format!(".{}$a", self.name);
+        let coff_stop = format!(".{}$c", self.name);
+
+        quote! {{
+            use #extern_trait::__private::Candidate;
+
+            #[cfg(any(#elf, #macho))]
+            unsafe extern "Rust" {
+                #[cfg_attr(#elf, link_name = #elf_start)]
+                #[cfg_attr(#macho, link_name = #macho_start)]
+                static START: Candidate;
+                #[cfg_attr(#elf, link_name = #elf_stop)]
+                #[cfg_attr(#macho, link_name = #macho_stop)]
+                static STOP: Candidate;
+            }
+
+            // Empty markers around the candidates, which the linker sorts
+            // between them.
+            #[cfg(#coff)]
+            #[unsafe(link_section = #coff_start)]
+            #[used]
+            static START: [Candidate; 0] = [];
+            #[cfg(#coff)]
+            #[unsafe(link_section = #coff_stop)]
+            #[used]
+            static STOP: [Candidate; 0] = [];
+
+            #[cfg(not(any(#elf, #macho, #coff)))]
+            compile_error!(
+                "`default = ...` with the `section-defaults` feature is not supported on this \
+                 target; use the `nightly-weak` feature instead"
+            );
+
+            (
+                (&raw const START).cast::<Candidate>(),
+                (&raw const STOP).cast::<Candidate>(),
+            )
+        }}
+    }
+}
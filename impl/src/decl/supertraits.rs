@@ -72,7 +72,6 @@ const SUPERTRAITS: LazyCell<Vec<Supertrait>> = LazyCell::new(|| {
     vec![
         supertrait! { unsafe Send {} },
         supertrait! { unsafe Sync {} },
-        supertrait! { Sized {} },
         supertrait! { Unpin {} },
         supertrait! { Copy {} },
         supertrait! { Eq {} },
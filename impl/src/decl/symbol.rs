@@ -1,51 +1,182 @@
-use std::env::var;
+use std::{
+    env::var,
+    fmt::{self, Display, Write},
+};
 
-use proc_macro::Span;
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use syn::{Error, Ident, Result};
 
-fn hash(string: &str) -> u64 {
-    use std::hash::{DefaultHasher, Hash, Hasher};
+/// 64-bit FNV-1a, which unlike std's `DefaultHasher` is specified and so
+/// gives the same result on every machine and toolchain.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
 
-    let mut hasher = DefaultHasher::new();
-    string.hash(&mut hasher);
-    hasher.finish()
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
 }
 
-// Code adapted from https://github.com/knurling-rs/defmt/blob/023449c35f68b9dfc2e00437e47353755d5189ef/macros/src/construct.rs
-fn crate_local_disambiguator() -> u64 {
-    // We want a deterministic, but unique-per-macro-invocation identifier. For that we
-    // hash the call site `Span`'s debug representation, which contains a counter that
-    // should disambiguate macro invocations within a crate.
-    hash(&format!("{:?}", Span::call_site()))
+/// Write `tokens` one per line, so the result does not depend on how the
+/// tokens happen to be spaced.
+fn write_tokens(tokens: TokenStream, out: &mut String) {
+    for token in tokens {
+        match token {
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::None => ("", ""),
+                };
+                out.push_str(open);
+                out.push('\n');
+                write_tokens(group.stream(), out);
+                out.push_str(close);
+            }
+            TokenTree::Ident(ident) => out.push_str(&ident.to_string()),
+            TokenTree::Punct(punct) => out.push(punct.as_char()),
+            TokenTree::Literal(literal) => out.push_str(&literal.to_string()),
+        }
+        out.push('\n');
+    }
+}
+
+/// Write `part` with every byte other than an ASCII alphanumeric, `_`, `-`
+/// or `.` escaped as `%XX`.
+fn escape(part: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for byte in part.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'_' | b'-' | b'.' => {
+                f.write_char(byte.into())?
+            }
+            _ => write!(f, "%{byte:02X}")?,
+        }
+    }
+    Ok(())
 }
 
+/// The linker symbol of a trait's VTable, exported by the implementation and
+/// imported by the proxy.
+///
+/// The symbol only depends on the package name and version, the crate name
+/// and the trait name, so it is the same wherever and with whichever
+/// toolchain the crate is built. Trait names are unique within a crate, as
+/// each trait's macro is exported from the crate root. `hash` is the FNV-1a
+/// hash of the other parts, each followed by a NUL byte.
+///
+/// `abi` is the [`fingerprint`] of the VTable layout. Proxies and
+/// implementations built against diverging versions of the trait then fail
+/// to link rather than disagree on the layout.
+///
+/// It is displayed as
+/// `extern_trait$v2$<package>$<version>$<crate>$<trait>$<hash>$<abi>$<hint>`,
+/// with the parts escaped and the hashes in 16 hex digits, which
+/// `extern_trait::symbol::decode` reverses. The hint ends up in the linker
+/// error of a missing implementation.
+///
+/// A trait may instead set its symbol with `symbol = "..."`, for linking
+/// objects from build systems that derive different package names. Such a
+/// symbol is used as written, without a fingerprint. Outside Cargo, which
+/// sets the package and crate names, it must.
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
-pub struct Symbol {
-    hint: String,
-    extern_trait: String,
-    package: String,
-    version: String,
-    crate_name: String,
-    package_disambiguator: u64,
-    trait_name: String,
-    local_disambiguator: u64,
+pub enum Symbol {
+    Derived {
+        package: String,
+        version: String,
+        crate_name: String,
+        trait_name: String,
+        hash: u64,
+        abi: u64,
+    },
+    Explicit(String),
 }
 
 impl Symbol {
-    pub fn new(trait_name: String) -> Self {
-        let hint = format!(
-            "missing #[extern_trait] impl for trait `{trait_name}` - add an #[extern_trait] impl \
-             block in the implementing crate",
-        );
-        Self {
-            hint,
-            extern_trait: "v0".to_string(),
-            package: var("CARGO_PKG_NAME").unwrap_or("<unknown>".to_string()),
-            version: var("CARGO_PKG_VERSION").unwrap_or("<unknown>".to_string()),
-            crate_name: var("CARGO_CRATE_NAME").unwrap_or("<unknown>".to_string()),
-            package_disambiguator: hash(var("CARGO_MANIFEST_PATH").as_deref().unwrap_or_default()),
+    /// The derived symbol of `trait_name`, declared as `ident`, or an error
+    /// reported at `ident` if the crate is not built by Cargo. A fallback
+    /// name would give the same trait in two crates the same symbol.
+    pub fn new(ident: &Ident, trait_name: String) -> Result<Self> {
+        let cargo_var = |name| {
+            var(name).map_err(|_| {
+                Error::new_spanned(
+                    ident,
+                    format!(
+                        "#[extern_trait] derives the VTable symbol from the Cargo package, but \
+                         `{name}` is not set; outside Cargo, set the symbol with `symbol = \
+                         \"...\"`"
+                    ),
+                )
+            })
+        };
+        let package = cargo_var("CARGO_PKG_NAME")?;
+        let version = cargo_var("CARGO_PKG_VERSION")?;
+        let crate_name = cargo_var("CARGO_CRATE_NAME")?;
+
+        let mut key = Vec::new();
+        for part in [&package, &version, &crate_name, &trait_name] {
+            key.extend_from_slice(part.as_bytes());
+            key.push(0);
+        }
+
+        Ok(Self::Derived {
+            package,
+            version,
+            crate_name,
             trait_name,
-            local_disambiguator: crate_local_disambiguator(),
+            hash: fnv1a(&key),
+            abi: 0,
+        })
+    }
+
+    /// Name of the link section collecting candidate VTables for the
+    /// `section-defaults` feature, short enough for Mach-O.
+    pub fn section_name(&self) -> String {
+        format!("et{:014x}", fnv1a(self.to_string().as_bytes()) >> 8)
+    }
+
+    /// Fold the [`fingerprint`] of the VTable layout into the symbol.
+    pub fn set_abi(&mut self, fingerprint: u64) {
+        if let Symbol::Derived { abi, .. } = self {
+            *abi = fingerprint;
+        }
+    }
+}
+
+/// Fingerprint of the VTable layout, as the FNV-1a hash of `vtable`, the
+/// tokens of the VTable struct.
+pub fn fingerprint(vtable: TokenStream) -> u64 {
+    let mut tokens = String::new();
+    write_tokens(vtable, &mut tokens);
+    fnv1a(tokens.as_bytes())
+}
+
+/// The package defining the trait and its version, e.g. `my-api 0.1.0`.
+pub fn package_version() -> String {
+    let package = var("CARGO_PKG_NAME").unwrap_or("<unknown>".to_string());
+    let version = var("CARGO_PKG_VERSION").unwrap_or("<unknown>".to_string());
+    format!("{package} {version}")
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Symbol::Derived {
+                package,
+                version,
+                crate_name,
+                trait_name,
+                hash,
+                abi,
+            } => {
+                f.write_str("extern_trait$v2")?;
+                for part in [package, version, crate_name, trait_name] {
+                    f.write_char('$')?;
+                    escape(part, f)?;
+                }
+                write!(f, "${hash:016x}${abi:016x}$add-an-extern_trait-impl")
+            }
+            Symbol::Explicit(symbol) => f.write_str(symbol),
         }
     }
 }
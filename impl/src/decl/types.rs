@@ -1,10 +1,15 @@
 use proc_macro2::TokenStream;
 use quote::{ToTokens, format_ident, quote};
 use syn::{
-    Error, FnArg, GenericArgument, Ident, Lifetime, PathArguments, Result, ReturnType, Signature,
-    Token, Type, TypePtr, TypeReference, parse_quote,
+    Abi, Attribute, Error, Expr, FnArg, GenericArgument, GenericParam, Ident, Lifetime, LitStr,
+    ParenthesizedGenericArguments, Path, PathArguments, Result, ReturnType, Signature, Token,
+    TraitBoundModifier, Type, TypeBareFn, TypeImplTrait, TypeParamBound, TypePath, TypePtr,
+    TypeReference, TypeSlice, parse_quote,
+    visit_mut::{self, VisitMut},
 };
 
+use super::opaque::OpaqueInfo;
+
 #[derive(Debug, Clone)]
 pub enum SelfKind {
     Value,
@@ -18,6 +23,10 @@ pub enum SelfKind {
         lifetime: Option<Lifetime>,
         mutability: Option<Token![mut]>,
     },
+    /// `Box<Self>`, `Rc<Self>` or `Arc<Self>`, with the path as written.
+    Smart(Path),
+    /// `Pin<P>` around a reference or smart pointer to `Self`.
+    Pin(Path, Box<SelfKind>),
 }
 
 impl SelfKind {
@@ -44,13 +53,53 @@ impl SelfKind {
                 mutability: *mutability,
                 elem: Box::new(elem.clone()),
             })),
+            SelfKind::Smart(path) => Box::new(with_type_arg(path, elem)),
+            SelfKind::Pin(path, inner) => Box::new(with_type_arg(path, &inner.to_type(elem))),
+        }
+    }
+
+    /// Name an elided reference lifetime `lifetime`, returning whether there was one.
+    fn name_elided_lifetime(&mut self, name: &Lifetime) -> bool {
+        match self {
+            SelfKind::Ref {
+                lifetime: lifetime @ None,
+                ..
+            } => {
+                *lifetime = Some(name.clone());
+                true
+            }
+            SelfKind::Pin(_, inner) => inner.name_elided_lifetime(name),
+            _ => false,
+        }
+    }
+
+    /// Whether the pointee lives in a heap allocation made for its own type,
+    /// which the other side of the VTable frees with its own layout.
+    pub fn is_heap(&self) -> bool {
+        match self {
+            SelfKind::Smart(_) => true,
+            SelfKind::Pin(_, inner) => inner.is_heap(),
+            _ => false,
         }
     }
 }
 
+/// `path` with the generic arguments of its last segment replaced by `<elem>`.
+fn with_type_arg(path: &Path, elem: &Type) -> Type {
+    let mut path = path.clone();
+    path.segments.last_mut().unwrap().arguments = PathArguments::None;
+    Type::Verbatim(quote!(#path<#elem>))
+}
+
 pub trait TypeExt {
     fn contains_self(&self) -> bool;
     fn self_kind(&self) -> Option<SelfKind>;
+    fn assoc_kind(&self) -> Option<(SelfKind, Ident)>;
+    /// Type arguments of a path type whose last segment is `name`.
+    fn generic_args(&self, name: &str) -> Option<Vec<&Type>>;
+    /// Type arguments of `name` from `core::module`, written unqualified or
+    /// with its full `core` or `std` path.
+    fn core_generic_args(&self, module: &str, name: &str) -> Option<Vec<&Type>>;
 }
 
 impl TypeExt for Type {
@@ -125,71 +174,844 @@ impl TypeExt for Type {
     }
 
     fn self_kind(&self) -> Option<SelfKind> {
-        let self_ty = parse_quote!(Self);
+        let (kind, elem) = peel_kind(self);
+        (*elem == parse_quote!(Self)).then_some(kind)
+    }
+
+    fn assoc_kind(&self) -> Option<(SelfKind, Ident)> {
+        let (kind, elem) = peel_kind(self);
+        if let Type::Path(TypePath { qself: None, path }) = elem
+            && path.leading_colon.is_none()
+            && path.segments.len() == 2
+            && path.segments[0].ident == "Self"
+            && path
+                .segments
+                .iter()
+                .all(|segment| segment.arguments.is_none())
+        {
+            Some((kind, path.segments[1].ident.clone()))
+        } else {
+            None
+        }
+    }
 
-        if *self == self_ty {
-            Some(SelfKind::Value)
-        } else if let Type::Ptr(TypePtr {
+    fn generic_args(&self, name: &str) -> Option<Vec<&Type>> {
+        let Type::Path(TypePath { qself: None, path }) = self else {
+            return None;
+        };
+        let last = path.segments.last()?;
+        if last.ident != name {
+            return None;
+        }
+        let PathArguments::AngleBracketed(args) = &last.arguments else {
+            return None;
+        };
+        args.args
+            .iter()
+            .map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn core_generic_args(&self, module: &str, name: &str) -> Option<Vec<&Type>> {
+        let Type::Path(TypePath { qself: None, path }) = self else {
+            return None;
+        };
+        let prefix: Vec<_> = path
+            .segments
+            .iter()
+            .rev()
+            .skip(1)
+            .map(|segment| match segment.arguments {
+                PathArguments::None => Some(segment.ident.to_string()),
+                _ => None,
+            })
+            .collect::<Option<_>>()?;
+        let core = match prefix.as_slice() {
+            [] => path.leading_colon.is_none(),
+            [m, krate] => m == module && (krate == "core" || krate == "std"),
+            _ => false,
+        };
+        core.then(|| self.generic_args(name)).flatten()
+    }
+}
+
+/// Split a type into its outer pointer, reference or smart pointer and the
+/// pointee.
+fn peel_kind(ty: &Type) -> (SelfKind, &Type) {
+    match ty {
+        Type::Ptr(TypePtr {
             star_token,
             const_token,
             mutability,
             elem,
-        }) = self
-        {
-            if **elem == self_ty {
-                Some(SelfKind::Ptr {
-                    star_token: *star_token,
-                    const_token: *const_token,
-                    mutability: *mutability,
-                })
-            } else {
-                None
-            }
-        } else if let Type::Reference(TypeReference {
+        }) => (
+            SelfKind::Ptr {
+                star_token: *star_token,
+                const_token: *const_token,
+                mutability: *mutability,
+            },
+            elem,
+        ),
+        Type::Reference(TypeReference {
             and_token,
             lifetime,
             mutability,
             elem,
-        }) = self
-        {
-            if **elem == self_ty {
-                Some(SelfKind::Ref {
-                    and_token: *and_token,
-                    lifetime: lifetime.clone(),
-                    mutability: *mutability,
-                })
-            } else {
-                None
+        }) => (
+            SelfKind::Ref {
+                and_token: *and_token,
+                lifetime: lifetime.clone(),
+                mutability: *mutability,
+            },
+            elem,
+        ),
+        Type::Path(TypePath { qself: None, path }) => {
+            for name in ["Box", "Rc", "Arc"] {
+                if let Some(args) = ty.generic_args(name)
+                    && let [elem] = args.as_slice()
+                {
+                    return (SelfKind::Smart(path.clone()), elem);
+                }
+            }
+            if let Some(args) = ty.generic_args("Pin")
+                && let [inner] = args.as_slice()
+                && let (kind @ (SelfKind::Ref { .. } | SelfKind::Smart(_)), elem) = peel_kind(inner)
+            {
+                return (SelfKind::Pin(path.clone(), Box::new(kind)), elem);
+            }
+            (SelfKind::Value, ty)
+        }
+        _ => (SelfKind::Value, ty),
+    }
+}
+
+/// Replace elided lifetimes in `ty` with `'static`, as in a `const` item.
+///
+/// Lifetimes inside `fn` pointers and `Fn` bounds are left untouched since
+/// elision there introduces higher-ranked lifetimes.
+pub fn with_static_lifetimes(ty: &Type) -> Type {
+    struct StaticLifetimes;
+
+    impl VisitMut for StaticLifetimes {
+        fn visit_type_reference_mut(&mut self, r: &mut TypeReference) {
+            if r.lifetime.is_none() {
+                r.lifetime = Some(parse_quote!('static));
+            }
+            visit_mut::visit_type_reference_mut(self, r);
+        }
+
+        fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+            if lifetime.ident == "_" {
+                *lifetime = parse_quote!('static);
             }
+        }
+
+        fn visit_type_bare_fn_mut(&mut self, _: &mut TypeBareFn) {}
+
+        fn visit_parenthesized_generic_arguments_mut(
+            &mut self,
+            _: &mut ParenthesizedGenericArguments,
+        ) {
+        }
+    }
+
+    let mut ty = ty.clone();
+    StaticLifetimes.visit_type_mut(&mut ty);
+    ty
+}
+
+/// Renames lifetimes of a type, such as to name the lifetimes the elided
+/// ones stand for.
+pub struct RenameLifetimes<'a> {
+    /// A named lifetime renamed to `to`.
+    pub from: Option<&'a Lifetime>,
+    pub to: &'a Lifetime,
+    /// Whether elided lifetimes are named `to` as well.
+    pub elided: bool,
+}
+
+impl VisitMut for RenameLifetimes<'_> {
+    fn visit_type_reference_mut(&mut self, r: &mut TypeReference) {
+        if self.elided && r.lifetime.is_none() {
+            r.lifetime = Some(self.to.clone());
+        }
+        visit_mut::visit_type_reference_mut(self, r);
+    }
+
+    fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+        if Some(&*lifetime) == self.from || (self.elided && lifetime.ident == "_") {
+            *lifetime = self.to.clone();
+        }
+    }
+
+    // Elision there introduces higher-ranked lifetimes.
+    fn visit_type_bare_fn_mut(&mut self, f: &mut TypeBareFn) {
+        let elided = std::mem::replace(&mut self.elided, false);
+        visit_mut::visit_type_bare_fn_mut(self, f);
+        self.elided = elided;
+    }
+
+    fn visit_parenthesized_generic_arguments_mut(
+        &mut self,
+        args: &mut ParenthesizedGenericArguments,
+    ) {
+        let elided = std::mem::replace(&mut self.elided, false);
+        visit_mut::visit_parenthesized_generic_arguments_mut(self, args);
+        self.elided = elided;
+    }
+}
+
+/// Replaces type parameters with concrete types.
+pub struct Substitute<'a>(pub &'a [(Ident, Type)]);
+
+impl VisitMut for Substitute<'_> {
+    fn visit_type_mut(&mut self, t: &mut Type) {
+        if let Type::Path(TypePath { qself: None, path }) = t
+            && let Some((_, ty)) = self.0.iter().find(|(param, _)| path.is_ident(param))
+        {
+            *t = ty.clone();
         } else {
-            None
+            visit_mut::visit_type_mut(self, t);
         }
     }
 }
 
+/// `sig` with its generic parameters removed and the type parameter `param`
+/// replaced by `ty` throughout.
+pub fn with_type_param(sig: &Signature, param: &Ident, ty: &Type) -> Signature {
+    let mut sig = sig.clone();
+    sig.generics.params = std::mem::take(&mut sig.generics.params)
+        .into_iter()
+        .filter(|param| !matches!(param, GenericParam::Type(_)))
+        .collect();
+    Substitute(&[(param.clone(), ty.clone())]).visit_signature_mut(&mut sig);
+    sig
+}
+
+/// Concrete types substituted for `Self` and its associated types when
+/// expanding a signature.
+#[derive(Debug, Clone)]
+pub struct SelfTypes {
+    pub this: Type,
+    pub assoc: Vec<(Ident, Type)>,
+    /// The implementation type of a `#[extern_trait(boxed)]` impl, whose
+    /// `this` is the `Box` stored in the proxy.
+    pub boxed: Option<Type>,
+}
+
+impl SelfTypes {
+    pub fn new(this: Type) -> Self {
+        Self {
+            this,
+            assoc: Vec::new(),
+            boxed: None,
+        }
+    }
+
+    /// The type whose trait methods are called.
+    pub fn impl_type(&self) -> &Type {
+        self.boxed.as_ref().unwrap_or(&self.this)
+    }
+
+    pub fn assoc(&self, ident: &Ident) -> Type {
+        self.assoc
+            .iter()
+            .find(|(name, _)| name == ident)
+            .map(|(_, ty)| ty.clone())
+            .unwrap_or_else(|| parse_quote!(Self::#ident))
+    }
+}
+
+/// Finds the first `impl Trait` in a type.
+struct NestedImplTrait(Option<TypeImplTrait>);
+
+impl VisitMut for NestedImplTrait {
+    fn visit_type_impl_trait_mut(&mut self, t: &mut TypeImplTrait) {
+        self.0.get_or_insert_with(|| t.clone());
+    }
+}
+
+fn nested_impl_trait(ty: TypeImplTrait) -> Error {
+    Error::new_spanned(
+        ty,
+        "#[extern_trait] `impl Trait` is only supported as the whole argument type",
+    )
+}
+
 #[derive(Debug, Clone)]
 pub enum MaybeSelf {
     Self_(SelfKind),
+    /// `Self::Assoc` in one of the [`SelfKind`] forms.
+    Assoc(SelfKind, Ident),
+    /// `[Self]` or `[Self::Assoc]` behind a reference, pointer or smart pointer.
+    Slice(SelfKind, Option<Ident>),
+    Option(Box<MaybeSelf>),
+    Result(Box<MaybeSelf>, Box<MaybeSelf>),
+    Tuple(Vec<MaybeSelf>),
+    Array(Box<MaybeSelf>, Box<Expr>),
+    /// Argument-position `impl Trait`, passed through the VTable as
+    /// `&mut dyn Trait`.
+    Dyn(Box<TypeImplTrait>),
     Typed(Box<Type>),
 }
 
 impl MaybeSelf {
-    pub fn to_type(&self, elem: &Type) -> Box<Type> {
+    fn try_new(ty: &Type) -> Result<Self> {
+        if !ty.contains_self() {
+            return Ok(MaybeSelf::Typed(Box::new(ty.clone())));
+        }
+        if peel_kind(ty).0.is_heap() && !cfg!(feature = "alloc") {
+            return Err(Error::new_spanned(
+                ty,
+                "`Box`, `Rc` and `Arc` of `Self` require the `alloc` feature",
+            ));
+        }
+        if let Some(kind) = ty.self_kind() {
+            return Ok(MaybeSelf::Self_(kind));
+        }
+        if let Some((kind, ident)) = ty.assoc_kind() {
+            return Ok(MaybeSelf::Assoc(kind, ident));
+        }
+        if let (kind, Type::Slice(slice)) = peel_kind(ty)
+            && !matches!(kind, SelfKind::Value)
+        {
+            let elem = &*slice.elem;
+            if elem
+                .self_kind()
+                .is_some_and(|kind| matches!(kind, SelfKind::Value))
+            {
+                return Ok(MaybeSelf::Slice(kind, None));
+            }
+            if let Some((SelfKind::Value, ident)) = elem.assoc_kind() {
+                return Ok(MaybeSelf::Slice(kind, Some(ident)));
+            }
+        }
+        if let Some(args) = ty.core_generic_args("option", "Option")
+            && let [inner] = args.as_slice()
+        {
+            return Ok(MaybeSelf::Option(Box::new(Self::try_new(inner)?)));
+        }
+        if let Some(args) = ty.core_generic_args("result", "Result")
+            && let [ok, err] = args.as_slice()
+        {
+            return Ok(MaybeSelf::Result(
+                Box::new(Self::try_new(ok)?),
+                Box::new(Self::try_new(err)?),
+            ));
+        }
+        // Aliases such as `io::Result<Self>` hide what they stand for
+        if ty.generic_args("Option").is_some() || ty.generic_args("Result").is_some() {
+            return Err(Error::new_spanned(
+                ty,
+                "#[extern_trait] only supports `Self` in `Option<T>` and `Result<T, E>` of \
+                 `core`; write out aliases such as `io::Result<Self>` as `Result<Self, io::Error>`",
+            ));
+        }
+        if let Type::Tuple(tuple) = ty {
+            let elems = tuple
+                .elems
+                .iter()
+                .map(Self::try_new)
+                .collect::<Result<_>>()?;
+            return Ok(MaybeSelf::Tuple(elems));
+        }
+        if let Type::Array(array) = ty {
+            return Ok(MaybeSelf::Array(
+                Box::new(Self::try_new(&array.elem)?),
+                Box::new(array.len.clone()),
+            ));
+        }
+        Err(Error::new_spanned(
+            ty,
+            "#[extern_trait] too complex `Self` type",
+        ))
+    }
+
+    /// Like [`MaybeSelf::try_new`], but also accepts `impl Trait`.
+    fn try_new_arg(ty: &Type) -> Result<Self> {
+        let mut nested = NestedImplTrait(None);
+        let Type::ImplTrait(impl_trait) = ty else {
+            nested.visit_type_mut(&mut ty.clone());
+            return match nested.0 {
+                Some(nested) => Err(nested_impl_trait(nested)),
+                None => Self::try_new(ty),
+            };
+        };
+        visit_mut::visit_type_impl_trait_mut(&mut nested, &mut impl_trait.clone());
+        if let Some(nested) = nested.0 {
+            return Err(nested_impl_trait(nested));
+        }
+        for bound in &impl_trait.bounds {
+            match bound {
+                TypeParamBound::Trait(t) if matches!(t.modifier, TraitBoundModifier::None) => {
+                    let path = Type::Path(TypePath {
+                        qself: None,
+                        path: t.path.clone(),
+                    });
+                    if path.contains_self() {
+                        return Err(Error::new_spanned(
+                            bound,
+                            "#[extern_trait] `impl Trait` arguments may not mention `Self`",
+                        ));
+                    }
+                }
+                // The argument is borrowed for the duration of the call only
+                TypeParamBound::Lifetime(_) => {
+                    return Err(Error::new_spanned(
+                        bound,
+                        "#[extern_trait] `impl Trait` arguments may not have lifetime bounds",
+                    ));
+                }
+                _ => {
+                    return Err(Error::new_spanned(
+                        bound,
+                        "#[extern_trait] unsupported bound on an `impl Trait` argument",
+                    ));
+                }
+            }
+        }
+        Ok(MaybeSelf::Dyn(Box::new(impl_trait.clone())))
+    }
+
+    /// The type as it appears in a method signature.
+    pub fn to_type(&self, types: &SelfTypes) -> Box<Type> {
         match self {
-            MaybeSelf::Self_(kind) => kind.to_type(elem),
+            MaybeSelf::Self_(kind) => kind.to_type(&types.this),
+            MaybeSelf::Assoc(kind, ident) => kind.to_type(&types.assoc(ident)),
+            MaybeSelf::Slice(kind, ident) => {
+                let elem = match ident {
+                    None => types.this.clone(),
+                    Some(ident) => types.assoc(ident),
+                };
+                kind.to_type(&Type::Slice(TypeSlice {
+                    bracket_token: Default::default(),
+                    elem: Box::new(elem),
+                }))
+            }
+            MaybeSelf::Option(inner) => {
+                let inner = inner.to_type(types);
+                Box::new(Type::Verbatim(quote!(::core::option::Option<#inner>)))
+            }
+            MaybeSelf::Result(ok, err) => {
+                let ok = ok.to_type(types);
+                let err = err.to_type(types);
+                Box::new(Type::Verbatim(quote!(::core::result::Result<#ok, #err>)))
+            }
+            MaybeSelf::Tuple(elems) => {
+                let elems = elems.iter().map(|elem| elem.to_type(types));
+                Box::new(Type::Verbatim(quote!((#(#elems,)*))))
+            }
+            MaybeSelf::Array(elem, len) => {
+                let elem = elem.to_type(types);
+                Box::new(Type::Verbatim(quote!([#elem; #len])))
+            }
+            MaybeSelf::Dyn(ty) => Box::new(Type::ImplTrait((**ty).clone())),
             MaybeSelf::Typed(ty) => ty.clone(),
         }
     }
 
-    pub fn is_self_value(&self) -> bool {
-        matches!(self, MaybeSelf::Self_(SelfKind::Value))
+    /// The type as it appears in a VTable function pointer, where by-value
+    /// `Self` and `Self::Assoc` are replaced with `repr`.
+    pub fn abi_type(&self, types: &SelfTypes, repr: &Type) -> Box<Type> {
+        match self {
+            MaybeSelf::Self_(SelfKind::Value) | MaybeSelf::Assoc(SelfKind::Value, _) => {
+                Box::new(repr.clone())
+            }
+            MaybeSelf::Option(inner) => {
+                let inner = inner.abi_type(types, repr);
+                Box::new(Type::Verbatim(quote!(::core::option::Option<#inner>)))
+            }
+            MaybeSelf::Result(ok, err) => {
+                let ok = ok.abi_type(types, repr);
+                let err = err.abi_type(types, repr);
+                Box::new(Type::Verbatim(quote!(::core::result::Result<#ok, #err>)))
+            }
+            MaybeSelf::Tuple(elems) => {
+                let elems = elems.iter().map(|elem| elem.abi_type(types, repr));
+                Box::new(Type::Verbatim(quote!((#(#elems,)*))))
+            }
+            MaybeSelf::Array(elem, len) => {
+                let elem = elem.abi_type(types, repr);
+                Box::new(Type::Verbatim(quote!([#elem; #len])))
+            }
+            MaybeSelf::Dyn(ty) => {
+                let bounds = &ty.bounds;
+                Box::new(Type::Verbatim(match bounds.len() {
+                    1 => quote!(&mut dyn #bounds),
+                    _ => quote!(&mut (dyn #bounds)),
+                }))
+            }
+            _ => self.to_type(types),
+        }
+    }
+
+    /// Name elided lifetimes `lifetime`, returning whether there were any.
+    pub fn name_elided_lifetimes(&mut self, lifetime: &Lifetime) -> bool {
+        match self {
+            MaybeSelf::Self_(kind) | MaybeSelf::Assoc(kind, _) | MaybeSelf::Slice(kind, _) => {
+                kind.name_elided_lifetime(lifetime)
+            }
+            MaybeSelf::Option(inner) | MaybeSelf::Array(inner, _) => {
+                inner.name_elided_lifetimes(lifetime)
+            }
+            MaybeSelf::Result(ok, err) => {
+                ok.name_elided_lifetimes(lifetime) | err.name_elided_lifetimes(lifetime)
+            }
+            MaybeSelf::Tuple(elems) => elems.iter_mut().fold(false, |named, elem| {
+                elem.name_elided_lifetimes(lifetime) | named
+            }),
+            MaybeSelf::Dyn(_) => false,
+            MaybeSelf::Typed(ty) => {
+                let elided = ty.clone();
+                RenameLifetimes {
+                    from: None,
+                    to: lifetime,
+                    elided: true,
+                }
+                .visit_type_mut(ty);
+                *ty != elided
+            }
+        }
+    }
+
+    /// Whether values of this type must be converted to cross the VTable.
+    pub fn is_converted(&self) -> bool {
+        match self {
+            MaybeSelf::Self_(kind) | MaybeSelf::Assoc(kind, _) => {
+                matches!(kind, SelfKind::Value)
+            }
+            MaybeSelf::Option(inner) => inner.is_converted(),
+            MaybeSelf::Result(ok, err) => ok.is_converted() || err.is_converted(),
+            MaybeSelf::Tuple(elems) => elems.iter().any(MaybeSelf::is_converted),
+            MaybeSelf::Array(elem, _) => elem.is_converted(),
+            MaybeSelf::Slice(..) | MaybeSelf::Dyn(_) | MaybeSelf::Typed(_) => false,
+        }
+    }
+
+    /// `Self` (`None`) or `Self::Assoc` types reachable from this type that are
+    /// reinterpreted in place as slice elements or heap allocations, and so
+    /// need exactly the layout of `Repr`.
+    pub fn exact_layout_elems(&self) -> Vec<Option<&Ident>> {
+        match self {
+            MaybeSelf::Slice(_, ident) => vec![ident.as_ref()],
+            MaybeSelf::Self_(kind) if kind.is_heap() => vec![None],
+            MaybeSelf::Assoc(kind, ident) if kind.is_heap() => vec![Some(ident)],
+            MaybeSelf::Option(inner) | MaybeSelf::Array(inner, _) => inner.exact_layout_elems(),
+            MaybeSelf::Result(ok, err) => [ok, err]
+                .iter()
+                .flat_map(|t| t.exact_layout_elems())
+                .collect(),
+            MaybeSelf::Tuple(elems) => elems
+                .iter()
+                .flat_map(MaybeSelf::exact_layout_elems)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Convert `expr` from the signature type into the VTable type.
+    ///
+    /// An `impl Trait` argument is borrowed, so `expr` must be a mutable place.
+    pub fn lower(&self, expr: TokenStream, repr: &Type) -> TokenStream {
+        if let MaybeSelf::Dyn(_) = self {
+            return quote!(&mut #expr);
+        }
+        self.convert(
+            expr,
+            &|_, expr| quote!(unsafe { <#repr>::from_value(#expr) }),
+        )
+    }
+
+    /// Convert `expr` from the VTable type back into the signature type.
+    ///
+    /// `&mut dyn Trait` implements most object-safe traits of the standard
+    /// library, except `Fn`, which is forwarded to by a closure instead.
+    pub fn lift(&self, expr: TokenStream, types: &SelfTypes, repr: &Type) -> TokenStream {
+        if let MaybeSelf::Dyn(ty) = self
+            && let Some(args) = ty.bounds.iter().find_map(|bound| match bound {
+                TypeParamBound::Trait(t) => {
+                    let segment = t.path.segments.last()?;
+                    match &segment.arguments {
+                        PathArguments::Parenthesized(args) if segment.ident == "Fn" => Some(args),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            })
+        {
+            let names: Vec<_> = (0..args.inputs.len())
+                .map(|i| format_ident!("__a{}", i))
+                .collect();
+            return quote!({
+                let __f = #expr;
+                move |#(#names),*| __f(#(#names),*)
+            });
+        }
+        self.convert(expr, &|this, expr| {
+            let ty = this.to_type(types);
+            quote!(unsafe { <#repr>::into_value::<#ty>(#expr) })
+        })
+    }
+
+    /// Why `Self` in this type cannot be stored in a `Box` by a
+    /// `#[extern_trait(boxed)]` impl, if it cannot.
+    ///
+    /// Only by-value `Self` can be converted both ways. References can only be
+    /// passed in, since the implementation cannot return a reference to the
+    /// `Box` that holds it.
+    pub fn unboxable(&self, output: bool) -> Option<&'static str> {
+        match self {
+            MaybeSelf::Self_(SelfKind::Value) => None,
+            MaybeSelf::Self_(SelfKind::Ref { .. }) if !output => None,
+            MaybeSelf::Self_(SelfKind::Pin(_, inner))
+                if !output && matches!(**inner, SelfKind::Ref { .. }) =>
+            {
+                None
+            }
+            MaybeSelf::Self_(SelfKind::Ref { .. } | SelfKind::Pin(..)) => {
+                Some("returns a reference to `Self`")
+            }
+            MaybeSelf::Self_(_) => Some("takes a pointer or smart pointer to `Self`"),
+            MaybeSelf::Slice(_, None) => Some("takes a slice of `Self`"),
+            MaybeSelf::Option(inner) | MaybeSelf::Array(inner, _) => inner.unboxable(output),
+            MaybeSelf::Result(ok, err) => ok.unboxable(output).or(err.unboxable(output)),
+            MaybeSelf::Tuple(elems) => elems.iter().find_map(|elem| elem.unboxable(output)),
+            _ => None,
+        }
+    }
+
+    /// Whether every value of this type holds a proxy or a value of its
+    /// associated types, which only the VTable can have produced.
+    pub fn holds_value(&self) -> bool {
+        match self {
+            MaybeSelf::Self_(kind) | MaybeSelf::Assoc(kind, _) => {
+                !matches!(kind, SelfKind::Ptr { .. })
+            }
+            MaybeSelf::Tuple(elems) => elems.iter().any(MaybeSelf::holds_value),
+            _ => false,
+        }
+    }
+
+    /// Whether this type mentions `Self` itself, not just `Self::Assoc`.
+    fn mentions_self(&self) -> bool {
+        match self {
+            MaybeSelf::Self_(_) | MaybeSelf::Slice(_, None) => true,
+            MaybeSelf::Option(inner) | MaybeSelf::Array(inner, _) => inner.mentions_self(),
+            MaybeSelf::Result(ok, err) => ok.mentions_self() || err.mentions_self(),
+            MaybeSelf::Tuple(elems) => elems.iter().any(MaybeSelf::mentions_self),
+            _ => false,
+        }
+    }
+
+    /// Convert `expr` from `Self` stored in a `Box` to the implementation type.
+    pub fn unbox(&self, expr: TokenStream) -> TokenStream {
+        self.convert_boxed(expr, &|kind, expr| match kind {
+            SelfKind::Ref { mutability, .. } => quote!(&#mutability **#expr),
+            SelfKind::Pin(_, inner) => {
+                let mutability = match &**inner {
+                    SelfKind::Ref { mutability, .. } => mutability,
+                    _ => &None,
+                };
+                // The proxy is `!Unpin`, so the `Box` stays put while pinned.
+                quote!(unsafe {
+                    ::core::pin::Pin::new_unchecked(
+                        &#mutability **::core::pin::Pin::into_inner_unchecked(#expr)
+                    )
+                })
+            }
+            _ => quote!(*#expr),
+        })
+    }
+
+    /// Convert `expr` from the implementation type to `Self` stored in a `Box`.
+    pub fn rebox(&self, expr: TokenStream, extern_trait: &Path) -> TokenStream {
+        self.convert_boxed(
+            expr,
+            &|_, expr| quote!(#extern_trait::__private::Box::new(#expr)),
+        )
+    }
+
+    /// Apply `leaf` to every `Self` reachable from `expr`.
+    fn convert_boxed(
+        &self,
+        expr: TokenStream,
+        leaf: &dyn Fn(&SelfKind, TokenStream) -> TokenStream,
+    ) -> TokenStream {
+        if !self.mentions_self() {
+            return expr;
+        }
+        match self {
+            MaybeSelf::Self_(kind) => leaf(kind, expr),
+            MaybeSelf::Option(inner) => {
+                let inner = inner.convert_boxed(quote!(__v), leaf);
+                quote!(#expr.map(|__v| #inner))
+            }
+            MaybeSelf::Result(ok, err) => {
+                let mut expr = expr;
+                if ok.mentions_self() {
+                    let ok = ok.convert_boxed(quote!(__v), leaf);
+                    expr = quote!(#expr.map(|__v| #ok));
+                }
+                if err.mentions_self() {
+                    let err = err.convert_boxed(quote!(__v), leaf);
+                    expr = quote!(#expr.map_err(|__v| #err));
+                }
+                expr
+            }
+            MaybeSelf::Tuple(elems) => {
+                let names: Vec<_> = (0..elems.len())
+                    .map(|i| format_ident!("__t{}", i))
+                    .collect();
+                let converted = elems
+                    .iter()
+                    .zip(&names)
+                    .map(|(elem, name)| elem.convert_boxed(quote!(#name), leaf));
+                quote!({
+                    let (#(#names,)*) = #expr;
+                    (#(#converted,)*)
+                })
+            }
+            MaybeSelf::Array(elem, _) => {
+                let elem = elem.convert_boxed(quote!(__v), leaf);
+                quote!(#expr.map(|__v| #elem))
+            }
+            _ => expr,
+        }
+    }
+
+    /// Apply `leaf` to every by-value `Self` or `Self::Assoc` reachable from `expr`.
+    fn convert(
+        &self,
+        expr: TokenStream,
+        leaf: &dyn Fn(&Self, TokenStream) -> TokenStream,
+    ) -> TokenStream {
+        if !self.is_converted() {
+            return expr;
+        }
+        match self {
+            MaybeSelf::Option(inner) => {
+                let inner = inner.convert(quote!(__v), leaf);
+                quote!(#expr.map(|__v| #inner))
+            }
+            MaybeSelf::Result(ok, err) => {
+                let mut expr = expr;
+                if ok.is_converted() {
+                    let ok = ok.convert(quote!(__v), leaf);
+                    expr = quote!(#expr.map(|__v| #ok));
+                }
+                if err.is_converted() {
+                    let err = err.convert(quote!(__v), leaf);
+                    expr = quote!(#expr.map_err(|__v| #err));
+                }
+                expr
+            }
+            MaybeSelf::Tuple(elems) => {
+                let names: Vec<_> = (0..elems.len())
+                    .map(|i| format_ident!("__t{}", i))
+                    .collect();
+                let converted = elems
+                    .iter()
+                    .zip(&names)
+                    .map(|(elem, name)| elem.convert(quote!(#name), leaf));
+                quote!({
+                    let (#(#names,)*) = #expr;
+                    (#(#converted,)*)
+                })
+            }
+            MaybeSelf::Array(elem, _) => {
+                let elem = elem.convert(quote!(__v), leaf);
+                quote!(#expr.map(|__v| #elem))
+            }
+            _ => leaf(self, expr),
+        }
+    }
+}
+
+/// Storage of the future returned by an `async fn` through the VTable.
+#[derive(Debug, Clone)]
+pub enum FutureKind {
+    /// `#[future(size = N)]`: stored inline in `N` bytes.
+    Inline(Expr),
+    /// `#[future(boxed)]`: stored on the heap.
+    Boxed,
+}
+
+impl FutureKind {
+    /// Take the `#[future(...)]` attribute off a method.
+    pub fn take(attrs: &mut Vec<Attribute>, sig: &Signature) -> Result<Option<Self>> {
+        let Some(pos) = attrs.iter().position(|attr| attr.path().is_ident("future")) else {
+            return Ok(None);
+        };
+        let attr = attrs.remove(pos);
+        if sig.asyncness.is_none() {
+            return Err(Error::new_spanned(
+                attr,
+                "#[future] is only allowed on async functions",
+            ));
+        }
+
+        let mut size = None;
+        let mut boxed = None;
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("size") {
+                size = Some(meta.value()?.parse::<Expr>()?);
+            } else if meta.path.is_ident("boxed") {
+                boxed = Some(meta.path);
+            } else {
+                return Err(meta.error("expected `size = ...` or `boxed`"));
+            }
+            Ok(())
+        })?;
+
+        match (size, boxed) {
+            (Some(size), None) => Ok(Some(Self::Inline(size))),
+            (None, Some(boxed)) if !cfg!(feature = "alloc") => Err(Error::new_spanned(
+                boxed,
+                "#[future(boxed)] requires the `alloc` feature",
+            )),
+            (None, Some(_)) => Ok(Some(Self::Boxed)),
+            _ => Err(Error::new_spanned(
+                attr,
+                "#[future] expects exactly one of `size = ...` or `boxed`",
+            )),
+        }
+    }
+
+    /// The type returned through the VTable for a future of `output`.
+    pub fn to_type(&self, extern_trait: &Path, output: Type) -> Type {
+        match self {
+            FutureKind::Inline(size) => {
+                Type::Verbatim(quote!(#extern_trait::InlineFuture<{ #size }, #output>))
+            }
+            FutureKind::Boxed => Type::Verbatim(quote!(#extern_trait::BoxFuture<#output>)),
+        }
+    }
+
+    /// Expression storing the future `expr` in its VTable return type.
+    pub fn store(&self, extern_trait: &Path, expr: TokenStream) -> TokenStream {
+        match self {
+            FutureKind::Inline(_) => quote!(#extern_trait::InlineFuture::new(#expr)),
+            FutureKind::Boxed => quote!(#extern_trait::BoxFuture::new(#expr)),
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct VerifiedSignature {
     pub unsafety: Option<Token![unsafe]>,
+    /// `extern "C"` or `extern "C-unwind"`, kept through the VTable;
+    /// `None` for the Rust ABI.
+    pub abi: Option<Abi>,
     pub ident: Ident,
+    /// Lifetime parameters, higher-ranked in the VTable fn pointer type.
+    pub lifetimes: Vec<Lifetime>,
+    /// Set for `async fn`, whose `output` is that of the future.
+    pub future: Option<FutureKind>,
+    /// Set for methods returning `impl Trait`, whose `output` is as written.
+    pub opaque: Option<OpaqueInfo>,
     pub inputs: Vec<MaybeSelf>,
     pub output: Option<MaybeSelf>,
 }
@@ -205,14 +1027,29 @@ impl VerifiedSignature {
         if sig.asyncness.is_some() {
             return Err(Error::new_spanned(
                 sig.asyncness,
-                "#[extern_trait] does not support async functions",
+                "#[extern_trait] async functions need #[future(size = N)] or #[future(boxed)]",
             ));
         }
-        if !sig.generics.params.is_empty() {
-            return Err(Error::new_spanned(
-                &sig.generics,
-                "#[extern_trait] does not support generic functions",
-            ));
+        let mut lifetimes = Vec::new();
+        for param in &sig.generics.params {
+            match param {
+                GenericParam::Lifetime(param) if param.bounds.is_empty() => {
+                    lifetimes.push(param.lifetime.clone())
+                }
+                GenericParam::Lifetime(param) => {
+                    return Err(Error::new_spanned(
+                        param,
+                        "#[extern_trait] does not support lifetime bounds",
+                    ));
+                }
+                _ => {
+                    return Err(Error::new_spanned(
+                        &sig.generics,
+                        "#[extern_trait] does not support generic functions; list the types to \
+                         instantiate them with in #[instances(...)]",
+                    ));
+                }
+            }
         }
         if sig.generics.where_clause.is_some() {
             return Err(Error::new_spanned(
@@ -226,60 +1063,169 @@ impl VerifiedSignature {
                 "#[extern_trait] does not support variadic functions",
             ));
         }
-        if let Some(abi) = &sig.abi
-            && abi.name.as_ref().is_none_or(|name| name.value() != "Rust")
+        // `extern fn` without a name is `extern "C" fn`, spelled out so the
+        // expansion does not repeat the `missing_abi` lint
+        let abi = match sig
+            .abi
+            .as_ref()
+            .map(|abi| abi.name.as_ref().map(LitStr::value))
         {
-            return Err(Error::new_spanned(
-                abi,
-                "#[extern_trait] does not support non-Rust ABI",
-            ));
-        }
+            None => None,
+            Some(Some(name)) if name == "Rust" => None,
+            Some(None) => sig.abi.clone().map(|abi| Abi {
+                name: Some(LitStr::new("C", abi.extern_token.span)),
+                ..abi
+            }),
+            Some(Some(name)) if name == "C" || name == "C-unwind" => sig.abi.clone(),
+            Some(Some(_)) => {
+                return Err(Error::new_spanned(
+                    &sig.abi,
+                    "#[extern_trait] only supports the \"Rust\", \"C\" and \"C-unwind\" ABIs",
+                ));
+            }
+        };
 
         let inputs = sig
             .inputs
             .iter()
             .map(|arg| match arg {
-                FnArg::Receiver(arg) => arg.ty.clone(),
-                FnArg::Typed(arg) => arg.ty.clone(),
-            })
-            .map(|ty| {
-                if ty.contains_self() {
-                    if let Some(kind) = ty.self_kind() {
-                        Ok(MaybeSelf::Self_(kind))
-                    } else {
-                        Err(Error::new_spanned(
-                            ty,
-                            "#[extern_trait] too complex `Self` type",
-                        ))
-                    }
-                } else {
-                    Ok(MaybeSelf::Typed(ty.clone()))
-                }
+                FnArg::Receiver(arg) => MaybeSelf::try_new(&arg.ty),
+                FnArg::Typed(arg) => MaybeSelf::try_new_arg(&arg.ty),
             })
             .collect::<Result<Vec<_>>>()?;
 
         let output = match &sig.output {
             ReturnType::Default => None,
-            ReturnType::Type(_, ty) => Some(if ty.contains_self() {
-                let Some(kind) = ty.self_kind() else {
-                    return Err(Error::new_spanned(
-                        ty,
-                        "#[extern_trait] too complex `Self` type",
-                    ));
-                };
-                MaybeSelf::Self_(kind)
-            } else {
-                MaybeSelf::Typed(ty.clone())
-            }),
+            ReturnType::Type(_, ty) if matches!(**ty, Type::ImplTrait(_)) => {
+                return Err(Error::new_spanned(
+                    ty,
+                    "#[extern_trait] methods returning `impl Trait` need #[opaque(size = N)]",
+                ));
+            }
+            ReturnType::Type(_, ty) => Some(MaybeSelf::try_new(ty)?),
         };
 
         Ok(Self {
             unsafety: sig.unsafety,
+            abi,
             ident: sig.ident.clone(),
+            lifetimes,
+            future: None,
+            opaque: None,
             inputs,
             output,
         })
     }
+
+    /// Verify the signature of a method whose future, if it is an
+    /// `async fn`, is stored as `future`, and whose returned `impl Trait`,
+    /// if any, is described by `opaque`.
+    pub fn try_new_with(
+        sig: &Signature,
+        future: Option<FutureKind>,
+        opaque: Option<OpaqueInfo>,
+    ) -> Result<Self> {
+        let mut sig = sig.clone();
+        if future.is_some() {
+            sig.asyncness = None;
+        }
+        let output = match opaque {
+            Some(_) => std::mem::replace(&mut sig.output, ReturnType::Default),
+            None => ReturnType::Default,
+        };
+
+        let mut verified = Self {
+            future,
+            ..Self::try_new(&sig)?
+        };
+
+        if verified.abi.is_some() && (verified.future.is_some() || opaque.is_some()) {
+            return Err(Error::new_spanned(
+                &sig.abi,
+                "#[extern_trait] `extern` methods may not be `async` or return `impl Trait`",
+            ));
+        }
+
+        if let (Some(mut opaque), ReturnType::Type(_, ty)) = (opaque, output) {
+            if verified.has_dyn_inputs() {
+                return Err(Error::new_spanned(
+                    ty,
+                    "#[extern_trait] methods returning `impl Trait` may not take `impl Trait` \
+                     arguments",
+                ));
+            }
+            // Unless the bounds name it, the returned value borrows from the
+            // receiver, whose lifetime the VTable names if it is elided.
+            if opaque.lifetime.is_none()
+                && let Some(MaybeSelf::Self_(SelfKind::Ref { lifetime, .. })) =
+                    verified.inputs.first()
+            {
+                opaque.receiver = lifetime.is_none();
+                opaque.lifetime = Some(lifetime.clone().unwrap_or(parse_quote!('__self)));
+            }
+            verified.output = Some(MaybeSelf::Typed(ty));
+            verified.opaque = Some(opaque);
+        }
+
+        Ok(verified)
+    }
+}
+
+impl VerifiedSignature {
+    /// Whether any argument is an `impl Trait` passed as `&mut dyn Trait`.
+    pub fn has_dyn_inputs(&self) -> bool {
+        self.inputs
+            .iter()
+            .any(|input| matches!(input, MaybeSelf::Dyn(_)))
+    }
+
+    /// The lifetime named in the VTable for the receiver's elided one, if a
+    /// returned `impl Trait` or the elided lifetimes of the output borrow from it.
+    ///
+    /// Elision does not pick the receiver's lifetime in a fn pointer type.
+    fn receiver_lifetime(&self) -> Option<Lifetime> {
+        if let Some(opaque) = &self.opaque {
+            return opaque.lifetime.clone().filter(|_| opaque.receiver);
+        }
+        let Some(MaybeSelf::Self_(SelfKind::Ref { lifetime: None, .. })) = self.inputs.first()
+        else {
+            return None;
+        };
+        let lifetime: Lifetime = parse_quote!('__self);
+        let mut output = self.output.clone()?;
+        output.name_elided_lifetimes(&lifetime).then_some(lifetime)
+    }
+
+    /// Lifetime parameters of the VTable fn pointer type.
+    pub fn vtable_lifetimes(&self) -> Vec<Lifetime> {
+        let mut lifetimes = self.lifetimes.clone();
+        lifetimes.extend(self.receiver_lifetime());
+        lifetimes
+    }
+
+    /// Inputs as passed through the VTable, with the receiver's lifetime
+    /// named if the output borrows from it.
+    pub fn vtable_inputs(&self) -> Vec<MaybeSelf> {
+        let mut inputs = self.inputs.clone();
+        if let Some(receiver) = self.receiver_lifetime()
+            && let Some(MaybeSelf::Self_(SelfKind::Ref { lifetime, .. })) = inputs.first_mut()
+        {
+            *lifetime = Some(receiver);
+        }
+        inputs
+    }
+
+    /// Output as returned through the VTable, with its elided lifetimes
+    /// named if they borrow from the receiver.
+    pub fn vtable_output(&self) -> Option<MaybeSelf> {
+        let mut output = self.output.clone();
+        if self.opaque.is_none()
+            && let (Some(output), Some(receiver)) = (&mut output, self.receiver_lifetime())
+        {
+            output.name_elided_lifetimes(&receiver);
+        }
+        output
+    }
 }
 
 pub fn arg_names(inputs: &[MaybeSelf]) -> Vec<Ident> {
@@ -294,10 +1240,24 @@ pub fn arg_names(inputs: &[MaybeSelf]) -> Vec<Ident> {
         .collect()
 }
 
-pub fn make_return_type(output: &Option<MaybeSelf>, self_type: &Type) -> ReturnType {
+/// Whether `sig` has a receiver or mentions `Self` in its argument or
+/// return types.
+pub fn mentions_self(sig: &Signature) -> bool {
+    let output = match &sig.output {
+        ReturnType::Default => false,
+        ReturnType::Type(_, ty) => ty.contains_self(),
+    };
+    output
+        || sig.inputs.iter().any(|arg| match arg {
+            FnArg::Receiver(_) => true,
+            FnArg::Typed(arg) => arg.ty.contains_self(),
+        })
+}
+
+pub fn make_return_type(output: &Option<MaybeSelf>, types: &SelfTypes) -> ReturnType {
     match output {
         None => ReturnType::Default,
-        Some(ty) => ReturnType::Type(parse_quote!(->), ty.to_type(self_type)),
+        Some(ty) => ReturnType::Type(parse_quote!(->), ty.to_type(types)),
     }
 }
 
@@ -305,25 +1265,27 @@ impl ToTokens for VerifiedSignature {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         let VerifiedSignature {
             unsafety,
+            abi,
             ident,
+            lifetimes,
+            future,
+            opaque: _,
             inputs,
             output,
         } = self;
 
-        let self_type: Type = parse_quote!(Self);
+        let self_types = SelfTypes::new(parse_quote!(Self));
 
         let arg_names = arg_names(inputs);
         let arg_types = inputs
             .iter()
-            .map(|input| input.to_type(&self_type))
+            .map(|input| input.to_type(&self_types))
             .collect::<Vec<_>>();
-        let output: ReturnType = match output {
-            None => ReturnType::Default,
-            Some(output) => ReturnType::Type(parse_quote!(->), output.to_type(&self_type)),
-        };
+        let output = make_return_type(output, &self_types);
+        let asyncness = future.as_ref().map(|_| quote!(async));
 
         tokens.extend(quote! {
-            #unsafety fn #ident(#(#arg_names: #arg_types),*) #output
+            #asyncness #unsafety #abi fn #ident<#(#lifetimes),*>(#(#arg_names: #arg_types),*) #output
         });
     }
 }
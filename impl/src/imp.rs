@@ -1,6 +1,8 @@
 use proc_macro2::{Span, TokenStream};
 use quote::{quote, quote_spanned};
-use syn::{ItemImpl, Result, spanned::Spanned};
+use syn::{
+    GenericArgument, GenericParam, ItemImpl, PathArguments, Result, parse_quote, spanned::Spanned,
+};
 
 use crate::args::ImplArgs;
 
@@ -9,10 +11,15 @@ pub fn expand(args: ImplArgs, input: ItemImpl) -> Result<TokenStream> {
         return Err(syn::Error::new(Span::call_site(), "expected a trait impl"));
     };
 
-    if !input.generics.params.is_empty() {
+    if input
+        .generics
+        .params
+        .iter()
+        .any(|param| !matches!(param, GenericParam::Lifetime(_)))
+    {
         return Err(syn::Error::new_spanned(
-            input.generics,
-            "#[extern_trait] impls may not have generics",
+            &input.generics,
+            "#[extern_trait] impls may only have lifetime parameters",
         ));
     }
 
@@ -23,20 +30,59 @@ pub fn expand(args: ImplArgs, input: ItemImpl) -> Result<TokenStream> {
         ));
     }
 
-    let extern_trait = args.extern_trait;
     let ty = &input.self_ty;
 
-    let assert = quote_spanned! {ty.span()=>
+    // The VTable is shared by every lifetime the impl is generic over, so
+    // the trait's lifetime arguments are left to inference.
+    let mut trait_ = trait_.clone();
+    let mut types = Vec::new();
+    if let PathArguments::AngleBracketed(generic_args) =
+        &mut trait_.segments.last_mut().unwrap().arguments
+    {
+        for arg in &mut generic_args.args {
+            match arg {
+                GenericArgument::Lifetime(lifetime) => *lifetime = parse_quote!('_),
+                arg => types.push(arg.clone()),
+            }
+        }
+    }
+
+    // Instantiations of a generic trait share one macro, which tells them
+    // apart by the generic arguments passed in brackets, and names the
+    // others by the path of the trait passed in braces.
+    let mut macro_path = trait_.clone();
+    macro_path.segments.last_mut().unwrap().arguments = PathArguments::None;
+
+    // The trait's macro names its `Repr`, whose size may be set per trait,
+    // down to zero, and which may require a non-null first word. It also
+    // tells services apart, whose implementations are never constructed.
+    // A boxed impl only stores a pointer to the implementation.
+    let assert = (!args.boxed).then(|| quote_spanned! {ty.span()=>
         const _: () = {
             assert!(
-                ::core::mem::size_of::<#ty>() <= ::core::mem::size_of::<#extern_trait::Repr>(),
+                !#macro_path!(@service) || ::core::mem::size_of::<#ty>() == 0,
+                concat!(stringify!(#ty), " must be zero-sized to implement a service #[extern_trait], whose implementation is never constructed")
+            );
+            assert!(
+                ::core::mem::size_of::<#macro_path!(@repr)>() != 0 || ::core::mem::size_of::<#ty>() == 0,
+                concat!(stringify!(#ty), " must be zero-sized to implement a zero_sized #[extern_trait]")
+            );
+            assert!(
+                ::core::mem::size_of::<#ty>() <= ::core::mem::size_of::<#macro_path!(@repr)>(),
                 concat!(stringify!(#ty), " is too large to be used with #[extern_trait]")
             );
             assert!(
-                ::core::mem::align_of::<#ty>() <= ::core::mem::align_of::<#extern_trait::Repr>(),
+                ::core::mem::align_of::<#ty>() <= ::core::mem::align_of::<#macro_path!(@repr)>(),
                 concat!(stringify!(#ty), " requires stricter alignment than #[extern_trait] can provide")
             );
         };
+    });
+
+    let boxed = args.boxed.then(|| quote!(boxed));
+    let invocation = if types.is_empty() {
+        quote!(#macro_path!(#boxed #trait_: #ty);)
+    } else {
+        quote!(#macro_path!(#boxed [#(#types),*] {#macro_path} #trait_: #ty);)
     };
 
     Ok(quote! {
@@ -44,6 +90,6 @@ pub fn expand(args: ImplArgs, input: ItemImpl) -> Result<TokenStream> {
 
         #assert
 
-        #trait_!(#trait_: #ty);
+        #invocation
     })
 }
@@ -0,0 +1,116 @@
+use core::{
+    future::Future,
+    marker::{PhantomData, PhantomPinned},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::storage::Storage;
+
+/// SAFETY: `ptr` must point to a pinned, live `F`.
+unsafe fn poll<F: Future>(ptr: *mut (), cx: &mut Context<'_>) -> Poll<F::Output> {
+    unsafe { Pin::new_unchecked(&mut *ptr.cast::<F>()).poll(cx) }
+}
+
+/// SAFETY: `ptr` must point to a live `F` that is never used again.
+unsafe fn drop_in_place<F>(ptr: *mut ()) {
+    unsafe { core::ptr::drop_in_place(ptr.cast::<F>()) }
+}
+
+/// Future returned through the VTable by an `async fn` with
+/// `#[future(size = N)]`, stored inline in `N` bytes.
+///
+/// The implementation's future must be at most `N` bytes and 16-byte
+/// aligned, which is checked when the implementation crate is compiled.
+#[doc(hidden)]
+#[repr(C)]
+pub struct InlineFuture<const N: usize, T> {
+    storage: Storage<N>,
+    poll: unsafe fn(*mut (), &mut Context<'_>) -> Poll<T>,
+    drop: unsafe fn(*mut ()),
+    // the stored future may be self-referential and need not be `Send` or `Sync`
+    _marker: PhantomData<(*mut (), PhantomPinned)>,
+}
+
+impl<const N: usize, T> InlineFuture<N, T> {
+    #[doc(hidden)]
+    #[inline]
+    pub fn new<F: Future<Output = T>>(future: F) -> Self {
+        Self {
+            storage: Storage::new(future),
+            poll: poll::<F>,
+            drop: drop_in_place::<F>,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<const N: usize, T> Future for InlineFuture<N, T> {
+    type Output = T;
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        // SAFETY: The stored future is pinned along with `self`.
+        unsafe {
+            let this = self.get_unchecked_mut();
+            (this.poll)(this.storage.as_mut_ptr(), cx)
+        }
+    }
+}
+
+impl<const N: usize, T> Drop for InlineFuture<N, T> {
+    #[inline]
+    fn drop(&mut self) {
+        // SAFETY: The stored future is dropped exactly once.
+        unsafe { (self.drop)(self.storage.as_mut_ptr()) }
+    }
+}
+
+/// Future returned through the VTable by an `async fn` with
+/// `#[future(boxed)]`, stored on the heap.
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+#[repr(C)]
+pub struct BoxFuture<T> {
+    ptr: *mut (),
+    poll: unsafe fn(*mut (), &mut Context<'_>) -> Poll<T>,
+    drop: unsafe fn(*mut ()),
+}
+
+#[cfg(feature = "alloc")]
+impl<T> BoxFuture<T> {
+    #[doc(hidden)]
+    #[inline]
+    pub fn new<F: Future<Output = T>>(future: F) -> Self {
+        /// SAFETY: `ptr` must come from `Box::<F>::into_raw` and never be used again.
+        unsafe fn drop_box<F>(ptr: *mut ()) {
+            drop(unsafe { alloc::boxed::Box::from_raw(ptr.cast::<F>()) })
+        }
+
+        Self {
+            ptr: alloc::boxed::Box::into_raw(alloc::boxed::Box::new(future)).cast(),
+            poll: poll::<F>,
+            drop: drop_box::<F>,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T> Future for BoxFuture<T> {
+    type Output = T;
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        // SAFETY: The boxed future never moves.
+        unsafe { (self.poll)(self.ptr, cx) }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T> Drop for BoxFuture<T> {
+    #[inline]
+    fn drop(&mut self) {
+        // SAFETY: The boxed future is dropped and deallocated exactly once.
+        unsafe { (self.drop)(self.ptr) }
+    }
+}
@@ -1,8 +1,19 @@
 #![no_std]
 #![doc = include_str!("../README.md")]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+mod future;
+mod storage;
+pub mod symbol;
+
 pub use extern_trait_impl::*;
 
+#[cfg(feature = "alloc")]
+pub use self::future::BoxFuture;
+pub use self::future::InlineFuture;
+
 /// Opaque representation used to store implementation types in proxy structs.
 ///
 /// This type is two pointers in size, which means implementation types must be
@@ -53,8 +64,281 @@ impl Repr {
     }
 }
 
+/// [`Repr`] whose first word is never null, set by `#[extern_trait(non_null)]`.
+///
+/// This gives the proxy a niche, so `Option<Proxy>` is the same size as
+/// `Proxy`. Every implementation type must itself be non-null in its first
+/// word, as `Box<T>`, `Arc<T>` and `&T` are.
+///
+/// The size, alignment and non-null constraints are checked at compile time.
+#[doc(hidden)]
+#[derive(Clone, Copy)]
+#[repr(C)]
+#[cfg_attr(target_pointer_width = "32", repr(align(8)))]
+pub struct NonNullRepr(
+    core::ptr::NonNull<()>,
+    *mut (),
+    // make this type `!Send + !Sync + !Unpin + !UnwindSafe + !RefUnwindSafe + !Freeze`
+    core::marker::PhantomData<(
+        &'static mut (),
+        core::cell::UnsafeCell<()>,
+        core::marker::PhantomPinned,
+    )>,
+);
+
+const _: () = assert!(size_of::<NonNullRepr>() == size_of::<Repr>());
+const _: () = assert!(size_of::<Option<NonNullRepr>>() == size_of::<Repr>());
+
+impl NonNullRepr {
+    #[doc(hidden)]
+    #[inline]
+    pub unsafe fn from_value<T: Sized>(value: T) -> Self {
+        const { assert!(size_of::<T>() <= size_of::<NonNullRepr>()) };
+        const { assert!(align_of::<T>() <= align_of::<NonNullRepr>()) };
+        let mut repr = core::mem::MaybeUninit::<NonNullRepr>::zeroed();
+        // SAFETY: We just asserted that T fits in NonNullRepr and does not require
+        // stricter alignment, and the caller ensures T is non-null in its first word.
+        unsafe {
+            core::ptr::write(repr.as_mut_ptr().cast::<T>(), value);
+            repr.assume_init()
+        }
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub unsafe fn into_value<T: Sized>(self) -> T {
+        const { assert!(size_of::<T>() <= size_of::<NonNullRepr>()) };
+        const { assert!(align_of::<T>() <= align_of::<NonNullRepr>()) };
+        // SAFETY: We require that T fits in NonNullRepr and does not require stricter
+        // alignment, and the caller ensures the NonNullRepr was created from a valid T.
+        unsafe { core::ptr::read((&self as *const NonNullRepr).cast::<T>()) }
+    }
+}
+
+/// Types whose first field is a pointer that is never null, the only
+/// implementation types `#[extern_trait(non_null)]` accepts.
+///
+/// Implement it for wrappers of such a pointer to use them with a
+/// `non_null` trait:
+///
+/// ```
+/// #[repr(transparent)]
+/// struct Handle(Box<u32>);
+///
+/// unsafe impl extern_trait::NonNullPointer for Handle {}
+/// ```
+///
+/// # Safety
+///
+/// The type must start with a pointer-sized, pointer-aligned field that is
+/// never null, as `#[repr(transparent)]` or `#[repr(C)]` structs whose first
+/// field implements this trait do.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not known to be non-null in its first word, which \
+               #[extern_trait(non_null)] requires",
+    label = "not a non-null pointer",
+    note = "`Box`, `Arc`, `Rc`, `NonNull` and references to sized types are; implement \
+            `extern_trait::NonNullPointer` for wrappers of them"
+)]
+pub unsafe trait NonNullPointer {}
+
+#[diagnostic::do_not_recommend]
+unsafe impl<T> NonNullPointer for &T {}
+#[diagnostic::do_not_recommend]
+unsafe impl<T> NonNullPointer for &mut T {}
+#[diagnostic::do_not_recommend]
+unsafe impl<T> NonNullPointer for core::ptr::NonNull<T> {}
+#[cfg(feature = "alloc")]
+#[diagnostic::do_not_recommend]
+unsafe impl<T> NonNullPointer for alloc::boxed::Box<T> {}
+#[cfg(feature = "alloc")]
+#[diagnostic::do_not_recommend]
+unsafe impl<T> NonNullPointer for alloc::rc::Rc<T> {}
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+#[diagnostic::do_not_recommend]
+unsafe impl<T> NonNullPointer for alloc::sync::Arc<T> {}
+
+/// [`Repr`] with the size and alignment set by
+/// `#[extern_trait(size = N, align = A)]`.
+///
+/// `N` is the size in bytes, and `A` is one of the `Align*` markers in
+/// `__private`, whose alignment the storage takes on.
+///
+/// The size and alignment constraints are checked at compile time.
+#[doc(hidden)]
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct SizedRepr<const N: usize, A>(
+    [A; 0],
+    [core::mem::MaybeUninit<u8>; N],
+    // make this type `!Send + !Sync + !Unpin + !UnwindSafe + !RefUnwindSafe + !Freeze`
+    core::marker::PhantomData<(
+        &'static mut (),
+        core::cell::UnsafeCell<()>,
+        core::marker::PhantomPinned,
+    )>,
+);
+
+impl<const N: usize, A> SizedRepr<N, A> {
+    #[doc(hidden)]
+    #[inline]
+    pub unsafe fn from_value<T: Sized>(value: T) -> Self {
+        const { assert!(size_of::<T>() <= size_of::<Self>()) };
+        const { assert!(align_of::<T>() <= align_of::<Self>()) };
+        let mut repr = Self(
+            [],
+            [core::mem::MaybeUninit::uninit(); N],
+            core::marker::PhantomData,
+        );
+        // SAFETY: We just asserted that T fits in Self and does not require stricter alignment.
+        unsafe { core::ptr::write((&mut repr as *mut Self).cast::<T>(), value) };
+        repr
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub unsafe fn into_value<T: Sized>(self) -> T {
+        const { assert!(size_of::<T>() <= size_of::<Self>()) };
+        const { assert!(align_of::<T>() <= align_of::<Self>()) };
+        // SAFETY: We require that T fits in Self and does not require stricter alignment,
+        // and the caller ensures the Self was created from a valid T.
+        unsafe { core::ptr::read((&self as *const Self).cast::<T>()) }
+    }
+}
+
+macro_rules! align_markers {
+    ($($name:ident = $align:literal),* $(,)?) => {
+        $(
+            #[doc(hidden)]
+            #[derive(Clone, Copy)]
+            #[repr(align($align))]
+            pub struct $name;
+        )*
+    };
+}
+
 #[doc(hidden)]
 pub mod __private {
+    #[cfg(feature = "alloc")]
+    #[doc(hidden)]
+    pub use alloc::boxed::Box;
+
     #[doc(hidden)]
     pub use typeid::ConstTypeId;
+
+    #[doc(hidden)]
+    pub use crate::storage::Storage;
+
+    /// A candidate VTable of a trait with a default implementation, in the
+    /// link section of the trait.
+    #[doc(hidden)]
+    #[repr(C)]
+    pub struct Candidate {
+        pub vtable: *const (),
+        /// 0 for the default, 1 for an `#[extern_trait] impl`.
+        pub priority: usize,
+    }
+
+    unsafe impl Sync for Candidate {}
+
+    /// The VTable of the candidate with the highest priority in
+    /// `start..stop`.
+    ///
+    /// # Safety
+    ///
+    /// `start..stop` must be the bounds of a link section of candidates.
+    #[doc(hidden)]
+    #[cold]
+    pub unsafe fn resolve(start: *const Candidate, stop: *const Candidate) -> *const () {
+        let mut best: Option<&Candidate> = None;
+        let mut entry = start;
+        while entry < stop {
+            let candidate = unsafe { &*entry };
+            // Null entries are padding inserted by the linker
+            if !candidate.vtable.is_null()
+                && best.is_none_or(|best| candidate.priority > best.priority)
+            {
+                best = Some(candidate);
+            }
+            entry = unsafe { entry.add(1) };
+        }
+        best.expect("#[extern_trait] default implementation was not linked")
+            .vtable
+    }
+
+    /// Whether `T` fits in the storage `R` of a proxy.
+    #[doc(hidden)]
+    pub const fn fits<T, R>() -> bool {
+        size_of::<T>() <= size_of::<R>() && align_of::<T>() <= align_of::<R>()
+    }
+
+    /// Store `value` in `R`, like `Repr::from_value` but checked at runtime
+    /// with [`fits`], as the cast methods of traits with boxed
+    /// implementations are instantiated with types that do not fit.
+    ///
+    /// # Safety
+    ///
+    /// `T` must fit in `R`, and `R` be valid with the bytes of `T`.
+    #[doc(hidden)]
+    #[inline]
+    pub unsafe fn store<T, R>(value: T) -> R {
+        let mut repr = core::mem::MaybeUninit::<R>::zeroed();
+        unsafe {
+            core::ptr::write(repr.as_mut_ptr().cast::<T>(), value);
+            repr.assume_init()
+        }
+    }
+
+    /// Take back the value of [`store`].
+    ///
+    /// # Safety
+    ///
+    /// `repr` must hold a `T`.
+    #[doc(hidden)]
+    #[inline]
+    pub unsafe fn load<R, T>(repr: R) -> T {
+        let repr = core::mem::ManuallyDrop::new(repr);
+        unsafe { core::ptr::read((&*repr as *const R).cast::<T>()) }
+    }
+
+    /// Compiles only if `T` may implement a `non_null` trait.
+    #[doc(hidden)]
+    pub const fn assert_non_null<T: crate::NonNullPointer>() {}
+
+    /// Report a proxy whose linked VTable has another layout than its own,
+    /// in debug builds.
+    #[doc(hidden)]
+    #[cold]
+    #[inline(never)]
+    pub fn layout_mismatch(trait_name: &str, proxy: (&str, u64), implementation: (&str, u64)) -> ! {
+        panic!(
+            "#[extern_trait] VTable layout mismatch for trait `{trait_name}`: the proxy was built \
+             against {} (layout {:016x}), but the implementation against {} (layout {:016x})",
+            proxy.0, proxy.1, implementation.0, implementation.1,
+        )
+    }
+
+    /// Alignment of [`Repr`](crate::Repr), for a [`SizedRepr`](crate::SizedRepr)
+    /// without an explicit `align`.
+    #[doc(hidden)]
+    #[derive(Clone, Copy)]
+    #[repr(C)]
+    #[cfg_attr(target_pointer_width = "32", repr(align(8)))]
+    pub struct AlignRepr([usize; 0]);
+
+    align_markers! {
+        Align1 = 1,
+        Align2 = 2,
+        Align4 = 4,
+        Align8 = 8,
+        Align16 = 16,
+        Align32 = 32,
+        Align64 = 64,
+        Align128 = 128,
+        Align256 = 256,
+        Align512 = 512,
+        Align1024 = 1024,
+        Align2048 = 2048,
+        Align4096 = 4096,
+    }
 }
@@ -0,0 +1,50 @@
+use core::mem::MaybeUninit;
+
+/// Inline storage of `N` bytes for a value whose type is only known to the
+/// implementation crate, such as the future of an `async fn` with
+/// `#[future(size = N)]` or the value of an `impl Trait` returned with
+/// `#[opaque(size = N)]`.
+///
+/// The storage is 16-byte aligned. Whether a value fits is checked at
+/// compile time, when the implementation crate stores it.
+#[doc(hidden)]
+#[repr(C, align(16))]
+pub struct Storage<const N: usize>([MaybeUninit<u8>; N]);
+
+impl<const N: usize> Storage<N> {
+    #[doc(hidden)]
+    #[inline]
+    pub fn new<T>(value: T) -> Self {
+        const {
+            assert!(
+                size_of::<T>() <= N,
+                "value is too large for the size given in #[future(size = ...)] or #[opaque(size \
+                 = ...)]"
+            )
+        };
+        const {
+            assert!(
+                align_of::<T>() <= align_of::<Self>(),
+                "value requires stricter alignment than #[future(size = ...)] or #[opaque(size = \
+                 ...)] can provide"
+            )
+        };
+        let mut storage = Self([MaybeUninit::uninit(); N]);
+        // SAFETY: We just asserted that T fits in the storage and does not
+        // require stricter alignment.
+        unsafe { core::ptr::write(storage.0.as_mut_ptr().cast::<T>(), value) };
+        storage
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn as_ptr(&self) -> *const () {
+        self.0.as_ptr().cast()
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn as_mut_ptr(&mut self) -> *mut () {
+        self.0.as_mut_ptr().cast()
+    }
+}
@@ -0,0 +1,229 @@
+//! Decoding of VTable symbol names.
+//!
+//! Every `#[extern_trait]` trait links its proxy to its implementation
+//! through one symbol, which shows up in `nm` and `objdump` output, linker
+//! maps and the linker error of a missing implementation. It has the form
+//!
+//! ```text
+//! extern_trait$v2$<package>$<version>$<crate>$<trait>$<hash>$<abi>$<hint>
+//! ```
+//!
+//! where each part escapes bytes other than ASCII alphanumerics, `_`, `-`
+//! and `.` as `%XX` in uppercase hex, `hash` is the 64-bit FNV-1a hash of
+//! the unescaped parts, each followed by a NUL byte, `abi` is a fingerprint
+//! of the VTable layout, both in 16 hex digits, and `hint` is a reminder to
+//! add an `#[extern_trait]` impl. [`decode`] turns such a symbol back into
+//! its parts:
+//!
+//! ```
+//! let symbol = extern_trait::symbol::decode(concat!(
+//!     "extern_trait$v2$my-api$0.1.0$my_api$Codec%3Cu32%3E$",
+//!     "276c58df34795f30$0123456789abcdef$add-an-extern_trait-impl",
+//! ))
+//! .unwrap();
+//! assert_eq!(symbol.package(), "my-api");
+//! assert_eq!(symbol.version(), "0.1.0");
+//! assert_eq!(symbol.crate_name(), "my_api");
+//! assert_eq!(symbol.trait_name().to_string(), "Codec<u32>");
+//! assert_eq!(symbol.abi(), 0x0123456789abcdef);
+//! ```
+
+use core::fmt;
+
+/// A VTable symbol, decoded by [`decode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Symbol<'a> {
+    package: Part<'a>,
+    version: Part<'a>,
+    crate_name: Part<'a>,
+    trait_name: Part<'a>,
+    abi: u64,
+}
+
+impl<'a> Symbol<'a> {
+    /// Name of the package defining the trait, e.g. `my-api`.
+    pub fn package(&self) -> Part<'a> {
+        self.package
+    }
+
+    /// Version of the package defining the trait, e.g. `0.1.0`.
+    pub fn version(&self) -> Part<'a> {
+        self.version
+    }
+
+    /// Name of the crate defining the trait, e.g. `my_api`.
+    pub fn crate_name(&self) -> Part<'a> {
+        self.crate_name
+    }
+
+    /// Name of the trait, with the arguments of an instantiation of a
+    /// generic trait, e.g. `Codec<u32>`.
+    pub fn trait_name(&self) -> Part<'a> {
+        self.trait_name
+    }
+
+    /// Fingerprint of the VTable layout. Proxies and implementations built
+    /// against diverging versions of a trait have different fingerprints,
+    /// and so do not link.
+    pub fn abi(&self) -> u64 {
+        self.abi
+    }
+}
+
+impl fmt::Display for Symbol<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "trait `{}` of crate `{}` ({} {})",
+            self.trait_name, self.crate_name, self.package, self.version
+        )
+    }
+}
+
+/// One part of a [`Symbol`], unescaped when displayed or compared with a
+/// `str`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Part<'a>(&'a str);
+
+impl<'a> Part<'a> {
+    /// The part as it appears in the symbol, still escaped.
+    pub fn as_escaped(&self) -> &'a str {
+        self.0
+    }
+
+    /// The unescaped bytes. The part must have been validated by [`decode`].
+    fn bytes(&self) -> impl Iterator<Item = u8> + 'a {
+        let mut escaped = self.0.as_bytes();
+        core::iter::from_fn(move || {
+            let (&byte, rest) = escaped.split_first()?;
+            if byte != b'%' {
+                escaped = rest;
+                return Some(byte);
+            }
+            let (hex, rest) = rest.split_at(2);
+            escaped = rest;
+            Some(hex_digit(hex[0])? << 4 | hex_digit(hex[1])?)
+        })
+    }
+}
+
+impl fmt::Display for Part<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buf = [0; 4];
+        let mut len = 0;
+        for byte in self.bytes() {
+            buf[len] = byte;
+            len += 1;
+            // `decode` checked that the bytes are UTF-8, so a character
+            // is complete after at most four bytes.
+            if let Ok(c) = core::str::from_utf8(&buf[..len]) {
+                f.write_str(c)?;
+                len = 0;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PartialEq<str> for Part<'_> {
+    fn eq(&self, other: &str) -> bool {
+        self.bytes().eq(other.bytes())
+    }
+}
+
+impl PartialEq<&str> for Part<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        *self == **other
+    }
+}
+
+fn hex_digit(digit: u8) -> Option<u8> {
+    match digit {
+        b'0'..=b'9' => Some(digit - b'0'),
+        b'A'..=b'F' => Some(digit - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Check that `part` is well escaped and unescapes to UTF-8.
+fn validate(part: &str) -> Option<Part<'_>> {
+    let mut bytes = part.as_bytes();
+    let mut utf8 = [0; 4];
+    let mut len = 0;
+    while let Some((&byte, rest)) = bytes.split_first() {
+        let byte = match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'_' | b'-' | b'.' => {
+                bytes = rest;
+                byte
+            }
+            b'%' if rest.len() >= 2 => {
+                bytes = &rest[2..];
+                match hex_digit(rest[0])? << 4 | hex_digit(rest[1])? {
+                    // Only the escaping of the macro is accepted, so that
+                    // equal parts are escaped alike.
+                    b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'_' | b'-' | b'.' => return None,
+                    byte => byte,
+                }
+            }
+            _ => return None,
+        };
+        utf8[len] = byte;
+        len += 1;
+        match core::str::from_utf8(&utf8[..len]) {
+            Ok(_) => len = 0,
+            Err(err) if err.error_len().is_none() && len < 4 => {}
+            Err(_) => return None,
+        }
+    }
+    (len == 0).then_some(Part(part))
+}
+
+/// 64-bit FNV-1a of `parts`, each followed by a NUL byte.
+fn hash(parts: &[Part<'_>]) -> u64 {
+    parts
+        .iter()
+        .flat_map(|part| part.bytes().chain([0]))
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+}
+
+/// Parse 16 lowercase hex digits.
+fn parse_hex(hex: &str) -> Option<u64> {
+    let valid = hex.len() == 16 && hex.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+    valid.then(|| u64::from_str_radix(hex, 16).ok())?
+}
+
+/// Decode an `#[extern_trait]` VTable symbol.
+///
+/// Returns `None` if `symbol` is not such a symbol, is malformed, or does
+/// not match its hash. Symbols set with `symbol = "..."` are not decoded.
+pub fn decode(symbol: &str) -> Option<Symbol<'_>> {
+    let mut parts = symbol.split('$');
+    if parts.next()? != "extern_trait" || parts.next()? != "v2" {
+        return None;
+    }
+    let package = validate(parts.next()?)?;
+    let version = validate(parts.next()?)?;
+    let crate_name = validate(parts.next()?)?;
+    let trait_name = validate(parts.next()?)?;
+
+    if hash(&[package, version, crate_name, trait_name]) != parse_hex(parts.next()?)? {
+        return None;
+    }
+    let abi = parse_hex(parts.next()?)?;
+
+    // The hint, which only needs to be present
+    parts.next()?;
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some(Symbol {
+        package,
+        version,
+        crate_name,
+        trait_name,
+        abi,
+    })
+}
@@ -0,0 +1,42 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(PlatformProxy)]
+trait Platform {
+    const NAME: &str;
+    const PAGE_SIZE: usize;
+    const FEATURES: [u8; 3];
+
+    fn new() -> Self;
+    fn pages(&self, bytes: usize) -> usize;
+}
+
+mod platform_impl {
+    use super::*;
+
+    struct Board;
+
+    #[extern_trait]
+    impl Platform for Board {
+        const NAME: &str = "board";
+        const PAGE_SIZE: usize = 4096;
+        const FEATURES: [u8; 3] = [1, 2, 3];
+
+        fn new() -> Self {
+            Board
+        }
+
+        fn pages(&self, bytes: usize) -> usize {
+            bytes.div_ceil(Self::PAGE_SIZE)
+        }
+    }
+}
+
+#[test]
+fn constants_are_read_through_the_vtable() {
+    assert_eq!(PlatformProxy::name(), "board");
+    assert_eq!(PlatformProxy::page_size(), 4096);
+    assert_eq!(PlatformProxy::features(), [1, 2, 3]);
+
+    let platform = PlatformProxy::new();
+    assert_eq!(platform.pages(PlatformProxy::page_size() + 1), 2);
+}
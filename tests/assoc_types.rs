@@ -0,0 +1,120 @@
+use std::{
+    fmt::{Debug, Display},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use extern_trait::extern_trait;
+
+static ERROR_DROPS: AtomicUsize = AtomicUsize::new(0);
+
+#[extern_trait(DeviceProxy)]
+trait Device {
+    type Error: Debug + Display + PartialEq;
+    type Handle: Copy;
+
+    fn new(id: u32) -> Self;
+    fn open(&self) -> Result<u32, Self::Error>;
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Self::Error>;
+    fn handle(&self) -> Self::Handle;
+    fn handle_id(handle: &Self::Handle) -> u32;
+    fn last_error(&self) -> Option<Self::Error>;
+}
+
+mod device_impl {
+    use super::*;
+
+    struct Disk(u32);
+
+    #[derive(Debug, PartialEq)]
+    enum DiskError {
+        NotFound(u32),
+        Empty,
+    }
+
+    impl Display for DiskError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                DiskError::NotFound(id) => write!(f, "disk {id} not found"),
+                DiskError::Empty => write!(f, "buffer is empty"),
+            }
+        }
+    }
+
+    impl Drop for DiskError {
+        fn drop(&mut self) {
+            ERROR_DROPS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    struct DiskHandle(u32);
+
+    #[extern_trait]
+    impl Device for Disk {
+        type Error = DiskError;
+        type Handle = DiskHandle;
+
+        fn new(id: u32) -> Self {
+            Self(id)
+        }
+
+        fn open(&self) -> Result<u32, Self::Error> {
+            if self.0 < 4 {
+                Ok(self.0)
+            } else {
+                Err(DiskError::NotFound(self.0))
+            }
+        }
+
+        fn read(&self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            if buf.is_empty() {
+                return Err(DiskError::Empty);
+            }
+            buf[0] = self.0 as u8;
+            Ok(1)
+        }
+
+        fn handle(&self) -> Self::Handle {
+            DiskHandle(self.0 + 100)
+        }
+
+        fn handle_id(handle: &Self::Handle) -> u32 {
+            handle.0
+        }
+
+        fn last_error(&self) -> Option<Self::Error> {
+            (self.0 == 0).then_some(DiskError::Empty)
+        }
+    }
+}
+
+#[test]
+fn proxy_maps_associated_types_to_opaque_proxies() {
+    let disk = DeviceProxy::new(2);
+    assert_eq!(disk.open(), Ok(2));
+
+    let mut buf = [0; 4];
+    assert_eq!(disk.read(&mut buf), Ok(1));
+    assert_eq!(buf[0], 2);
+
+    let handle = disk.handle();
+    let copy = handle;
+    assert_eq!(DeviceProxy::handle_id(&handle), 102);
+    assert_eq!(DeviceProxy::handle_id(&copy), 102);
+    assert!(disk.last_error().is_none());
+}
+
+#[test]
+fn associated_type_proxy_forwards_bounds_and_drop() {
+    ERROR_DROPS.store(0, Ordering::SeqCst);
+
+    let err = DeviceProxy::new(7).open().unwrap_err();
+    assert_eq!(format!("{err}"), "disk 7 not found");
+    assert_eq!(format!("{err:?}"), "NotFound(7)");
+    drop(err);
+    assert_eq!(ERROR_DROPS.load(Ordering::SeqCst), 1);
+
+    let disk = DeviceProxy::new(0);
+    assert_eq!(disk.read(&mut []), Err(disk.last_error().unwrap()));
+    assert_eq!(ERROR_DROPS.load(Ordering::SeqCst), 3);
+}
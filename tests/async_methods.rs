@@ -0,0 +1,159 @@
+use std::{
+    cell::Cell,
+    future::Future,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+use extern_trait::extern_trait;
+
+/// Poll `future` to completion, yielding back to it whenever it is pending.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+/// Pending once, then ready.
+async fn yield_now() {
+    let mut yielded = false;
+    std::future::poll_fn(|_| {
+        if yielded {
+            Poll::Ready(())
+        } else {
+            yielded = true;
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+thread_local! {
+    static DROPPED: Cell<usize> = const { Cell::new(0) };
+}
+
+#[extern_trait(DeviceProxy)]
+trait Device: Sized {
+    fn new(base: u8) -> Self;
+    #[future(size = 128)]
+    async fn read(&self, buf: &mut [u8]) -> usize;
+    #[future(size = 64)]
+    async fn reset(&mut self);
+    #[future(size = 128)]
+    async fn fork(&self) -> Self;
+    #[future(size = 64)]
+    async fn first<'a>(&self, bufs: &'a [&'a [u8]]) -> Option<&'a [u8]>;
+    fn base(&self) -> u8;
+}
+
+mod device_impl {
+    use super::*;
+
+    struct Register(u8);
+
+    impl Drop for Register {
+        fn drop(&mut self) {
+            DROPPED.with(|dropped| dropped.set(dropped.get() + 1));
+        }
+    }
+
+    #[extern_trait]
+    impl Device for Register {
+        fn new(base: u8) -> Self {
+            Register(base)
+        }
+
+        async fn read(&self, buf: &mut [u8]) -> usize {
+            for (i, byte) in buf.iter_mut().enumerate() {
+                yield_now().await;
+                *byte = self.0 + i as u8;
+            }
+            buf.len()
+        }
+
+        async fn reset(&mut self) {
+            yield_now().await;
+            self.0 = 0;
+        }
+
+        async fn fork(&self) -> Self {
+            yield_now().await;
+            Register(self.0 + 1)
+        }
+
+        async fn first<'a>(&self, bufs: &'a [&'a [u8]]) -> Option<&'a [u8]> {
+            bufs.first().copied()
+        }
+
+        fn base(&self) -> u8 {
+            self.0
+        }
+    }
+}
+
+#[test]
+fn async_methods() {
+    let mut device = DeviceProxy::new(10);
+    let mut buf = [0; 4];
+    assert_eq!(block_on(device.read(&mut buf)), 4);
+    assert_eq!(buf, [10, 11, 12, 13]);
+
+    let fork = block_on(device.fork());
+    assert_eq!(fork.base(), 11);
+
+    block_on(device.reset());
+    assert_eq!(device.base(), 0);
+
+    let bufs: [&[u8]; 2] = [&[1, 2], &[3]];
+    assert_eq!(block_on(device.first(&bufs)), Some(&[1, 2][..]));
+}
+
+#[test]
+fn async_future_dropped_unpolled() {
+    DROPPED.with(|dropped| dropped.set(0));
+    let device = DeviceProxy::new(0);
+    drop(device.fork());
+    assert_eq!(DROPPED.with(Cell::get), 0);
+    drop(device);
+    assert_eq!(DROPPED.with(Cell::get), 1);
+}
+
+#[cfg(feature = "alloc")]
+mod boxed {
+    use super::*;
+
+    #[extern_trait(StreamProxy)]
+    trait Stream {
+        fn new() -> Self;
+        #[future(boxed)]
+        async fn next(&mut self) -> Option<[u64; 16]>;
+    }
+
+    struct Counter(u64);
+
+    #[extern_trait]
+    impl Stream for Counter {
+        fn new() -> Self {
+            Counter(0)
+        }
+
+        async fn next(&mut self) -> Option<[u64; 16]> {
+            let chunk = [self.0; 16];
+            yield_now().await;
+            self.0 += 1;
+            (self.0 <= 2).then_some(chunk)
+        }
+    }
+
+    #[test]
+    fn boxed_future() {
+        let mut stream = StreamProxy::new();
+        assert_eq!(block_on(stream.next()), Some([0; 16]));
+        assert_eq!(block_on(stream.next()), Some([1; 16]));
+        assert_eq!(block_on(stream.next()), None);
+    }
+}
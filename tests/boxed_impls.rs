@@ -0,0 +1,136 @@
+#![cfg(feature = "alloc")]
+
+use std::{
+    pin::Pin,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use extern_trait::extern_trait;
+
+static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+#[extern_trait(LogProxy)]
+trait Log: Clone + Sized {
+    fn new(name: &'static str) -> Self;
+    fn try_new(name: &'static str) -> Option<Self>;
+    fn split(self) -> (Self, Self);
+    fn push(&mut self, line: u64);
+    fn pin_push(self: Pin<&mut Self>, line: u64);
+    fn merge(&mut self, other: Self);
+    fn same(&self, other: &Self) -> bool;
+    fn lines(&self) -> &[u64];
+    fn into_len(self) -> usize;
+}
+
+mod log_impl {
+    use super::*;
+
+    // Far larger than `Repr`.
+    #[derive(Clone)]
+    pub struct MemLog {
+        pub name: &'static str,
+        pub lines: Vec<u64>,
+        pub scratch: [u64; 8],
+    }
+
+    impl Drop for MemLog {
+        fn drop(&mut self) {
+            if self.name == "drops" {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    #[extern_trait(boxed)]
+    impl Log for MemLog {
+        fn new(name: &'static str) -> Self {
+            MemLog {
+                name,
+                lines: Vec::new(),
+                scratch: [0; 8],
+            }
+        }
+
+        fn try_new(name: &'static str) -> Option<Self> {
+            (!name.is_empty()).then(|| Self::new(name))
+        }
+
+        fn split(self) -> (Self, Self) {
+            (self.clone(), self)
+        }
+
+        fn push(&mut self, line: u64) {
+            self.scratch[self.lines.len() % 8] = line;
+            self.lines.push(line);
+        }
+
+        fn pin_push(self: Pin<&mut Self>, line: u64) {
+            self.get_mut().push(line);
+        }
+
+        fn merge(&mut self, other: Self) {
+            self.lines.extend(&other.lines);
+        }
+
+        fn same(&self, other: &Self) -> bool {
+            self.name == other.name
+        }
+
+        fn lines(&self) -> &[u64] {
+            &self.lines
+        }
+
+        fn into_len(self) -> usize {
+            self.lines.len()
+        }
+    }
+}
+
+use log_impl::MemLog;
+
+#[test]
+fn boxed_methods() {
+    let mut log = std::pin::pin!(LogProxy::new("a"));
+    log.as_mut().pin_push(1);
+    log.as_mut().pin_push(2);
+    assert_eq!(log.lines(), [1, 2]);
+    let log = log.clone();
+
+    let (left, mut right) = log.split();
+    right.push(3);
+    assert!(left.same(&right));
+    assert_eq!(right.lines(), [1, 2, 3]);
+
+    let mut merged = LogProxy::try_new("b").unwrap();
+    merged.merge(right);
+    assert_eq!(merged.lines(), [1, 2, 3]);
+    assert!(!merged.same(&left));
+    assert!(LogProxy::try_new("").is_none());
+    assert_eq!(merged.into_len(), 3);
+}
+
+#[test]
+fn boxed_downcast() {
+    let mut log = LogProxy::from_impl(MemLog {
+        name: "c",
+        lines: vec![4],
+        scratch: [0; 8],
+    });
+    assert_eq!(log.downcast_ref::<MemLog>().lines, [4]);
+    log.downcast_mut::<MemLog>().lines.push(5);
+    assert_eq!(log.lines(), [4, 5]);
+
+    let log = log.into_impl::<MemLog>();
+    assert_eq!(log.name, "c");
+    assert_eq!(log.lines, [4, 5]);
+}
+
+#[test]
+fn boxed_dropped_once() {
+    let log = LogProxy::new("drops");
+    let (left, right) = log.split();
+    drop(left);
+    assert_eq!(DROPS.load(Ordering::SeqCst), 1);
+    drop(right);
+    assert_eq!(DROPS.load(Ordering::SeqCst), 2);
+}
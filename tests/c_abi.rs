@@ -0,0 +1,232 @@
+use std::{
+    ffi::{CStr, c_char, c_int},
+    ptr::NonNull,
+};
+
+use extern_trait::extern_trait;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[extern_trait(abi = "C", Counter)]
+trait CounterIf {
+    const STEP: u32;
+
+    fn new(start: u32) -> Self;
+    fn bump(&mut self) -> u32;
+    fn get(&self) -> u32;
+    fn origin(&self, out: Option<&mut Point>) -> bool;
+    fn name(&self) -> *const c_char;
+    unsafe fn add(&mut self, by: NonNull<u32>);
+    fn merge(self, other: Self) -> Self;
+    extern "C-unwind" fn reset(&mut self);
+}
+
+// Implemented in "C" below
+#[extern_trait(abi = "C", symbol = "c_abi_shape", size = 8, align = 4, Shape)]
+trait ShapeIf {
+    fn new(sides: c_int) -> Self;
+    fn sides(&self) -> c_int;
+}
+
+#[extern_trait(abi = "C", service, symbol = "c_abi_platform", Platform)]
+#[allow(dead_code)]
+trait PlatformIf {
+    fn page_size() -> usize;
+}
+
+mod impls {
+    use super::*;
+
+    struct Count(u32, u32);
+
+    #[extern_trait]
+    impl CounterIf for Count {
+        const STEP: u32 = 2;
+
+        fn new(start: u32) -> Self {
+            Count(start, 0)
+        }
+
+        fn bump(&mut self) -> u32 {
+            self.0 += Self::STEP;
+            self.1 += 1;
+            self.0
+        }
+
+        fn get(&self) -> u32 {
+            self.0
+        }
+
+        fn origin(&self, out: Option<&mut Point>) -> bool {
+            match out {
+                Some(out) => {
+                    *out = Point { x: 0, y: 0 };
+                    true
+                }
+                None => false,
+            }
+        }
+
+        fn name(&self) -> *const c_char {
+            c"count".as_ptr()
+        }
+
+        unsafe fn add(&mut self, by: NonNull<u32>) {
+            self.0 += unsafe { *by.as_ptr() };
+        }
+
+        fn merge(self, other: Self) -> Self {
+            Count(self.0 + other.0, self.1 + other.1)
+        }
+
+        extern "C-unwind" fn reset(&mut self) {
+            self.0 = 0;
+        }
+    }
+}
+
+// A VTable laid out as the generated header declares it, as a C object
+// file would provide.
+mod foreign {
+    use super::*;
+
+    #[repr(C)]
+    struct ShapeRepr {
+        sides: c_int,
+        _pad: c_int,
+    }
+
+    #[repr(C)]
+    struct ShapeVTable {
+        layout_hash: u64,
+        layout_version: *const c_char,
+        drop: unsafe extern "C" fn(*mut ShapeRepr),
+        new: extern "C" fn(c_int) -> ShapeRepr,
+        sides: extern "C" fn(*const ShapeRepr) -> c_int,
+    }
+
+    unsafe impl Sync for ShapeVTable {}
+
+    unsafe extern "C" fn drop(_: *mut ShapeRepr) {}
+
+    extern "C" fn new(sides: c_int) -> ShapeRepr {
+        ShapeRepr { sides, _pad: 0 }
+    }
+
+    extern "C" fn sides(this: *const ShapeRepr) -> c_int {
+        unsafe { (*this).sides }
+    }
+
+    #[unsafe(export_name = "c_abi_shape")]
+    static SHAPE_VTABLE: ShapeVTable = ShapeVTable {
+        layout_hash: Shape::LAYOUT_HASH,
+        layout_version: c"shapes-in-c 1.0".as_ptr(),
+        drop,
+        new,
+        sides,
+    };
+
+    #[repr(C)]
+    struct PlatformVTable {
+        layout_hash: u64,
+        layout_version: *const c_char,
+        drop: unsafe extern "C" fn(*mut ()),
+        page_size: extern "C" fn() -> usize,
+    }
+
+    unsafe impl Sync for PlatformVTable {}
+
+    extern "C" fn page_size() -> usize {
+        4096
+    }
+
+    #[unsafe(export_name = "c_abi_platform")]
+    static PLATFORM_VTABLE: PlatformVTable = PlatformVTable {
+        layout_hash: Platform::LAYOUT_HASH,
+        layout_version: core::ptr::null(),
+        drop: {
+            unsafe extern "C" fn drop(_: *mut ()) {}
+            drop
+        },
+        page_size,
+    };
+}
+
+#[test]
+fn rust_implementation() {
+    let mut counter = Counter::new(1);
+    assert_eq!(counter.bump(), 3);
+    assert_eq!(Counter::step(), 2);
+
+    let mut point = Point { x: 1, y: 1 };
+    assert!(counter.origin(Some(&mut point)));
+    assert_eq!(point, Point { x: 0, y: 0 });
+    assert!(!counter.origin(None));
+    assert_eq!(unsafe { CStr::from_ptr(counter.name()) }, c"count");
+
+    let mut by = 10;
+    unsafe { counter.add(NonNull::from(&mut by)) };
+    let mut counter = counter.merge(Counter::new(4));
+    assert_eq!(counter.get(), 17);
+    counter.reset();
+    assert_eq!(counter.get(), 0);
+}
+
+#[test]
+fn foreign_implementation() {
+    let shape = Shape::new(3);
+    assert_eq!(shape.sides(), 3);
+    assert_eq!(Platform::page_size(), 4096);
+}
+
+#[test]
+fn c_header() {
+    let header = Counter::C_HEADER;
+    let hash = format!(
+        "#define COUNTERIF_LAYOUT_HASH UINT64_C(0x{:016x})",
+        Counter::LAYOUT_HASH
+    );
+    assert!(header.contains(&hash));
+    let version = format!(
+        "#define COUNTERIF_LAYOUT_VERSION \"extern-trait {}\"",
+        env!("CARGO_PKG_VERSION")
+    );
+    assert!(header.contains(&version));
+    assert!(
+        header.contains(
+            "typedef struct CounterIf_Repr { _Alignas(8) void *words[2]; } CounterIf_Repr;"
+        )
+    );
+    for field in [
+        "uint64_t layout_hash;",
+        "char const *layout_version;",
+        "void (*drop)(CounterIf_Repr *);",
+        "uint32_t STEP;",
+        "CounterIf_Repr (*new)(uint32_t);",
+        "uint32_t (*bump)(CounterIf_Repr *);",
+        "uint32_t (*get)(CounterIf_Repr const *);",
+        "bool (*origin)(CounterIf_Repr const *, Point *);",
+        "char const * (*name)(CounterIf_Repr const *);",
+        "void (*add)(CounterIf_Repr *, uint32_t *);",
+        "CounterIf_Repr (*merge)(CounterIf_Repr, CounterIf_Repr);",
+        "void (*reset)(CounterIf_Repr *);",
+    ] {
+        assert!(header.contains(field), "missing `{field}` in\n{header}");
+    }
+    assert!(header.contains("extern CounterIf_VTable const CounterIf_vtable"));
+
+    let header = Shape::C_HEADER;
+    assert!(header.contains("#define SHAPEIF_VTABLE_SYMBOL \"c_abi_shape\""));
+    assert!(header.contains(
+        "typedef struct ShapeIf_Repr { _Alignas(4) unsigned char bytes[8]; } ShapeIf_Repr;"
+    ));
+
+    let header = Platform::C_HEADER;
+    assert!(header.contains("typedef struct PlatformIf_Repr PlatformIf_Repr;"));
+    assert!(header.contains("uintptr_t (*page_size)(void);"));
+}
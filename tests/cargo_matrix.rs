@@ -7,7 +7,9 @@ fn cargo_matrix() {
         .env("CARGO_TERM_COLOR", "never")
         .register_bin("cargo", PathBuf::from(env::var("CARGO").unwrap()))
         .timeout(Duration::from_secs(180))
-        .case("tests/cmd/default-requires-feature.toml");
+        .case("tests/cmd/default-requires-feature.toml")
+        .case("tests/cmd/boxed-requires-alloc.toml")
+        .case("tests/cmd/section-default-override.toml");
 
     if rustversion::cfg!(nightly) {
         cases.case("tests/cmd/nightly-weak-override.toml");
@@ -0,0 +1,177 @@
+use std::{
+    fmt::{Debug, Display},
+    future::Future,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+use extern_trait::extern_trait;
+
+#[extern_trait(direct, symbol = "direct_calls_stack", size = 24, StackProxy)]
+trait Stack: Debug {
+    fn new() -> Self;
+    fn push(&mut self, value: u32);
+    fn pop(&mut self) -> Option<u32>;
+    fn top(&self) -> Option<&u32>;
+    fn merge(self, other: Self) -> Self;
+    #[instances(u8, u64)]
+    fn sum<T: TryFrom<u64>>(&self) -> Option<T>;
+    unsafe fn get_unchecked(&self, index: usize) -> u32;
+}
+
+#[extern_trait(direct, CursorProxy)]
+trait Cursor<'src> {
+    fn new() -> Self;
+    fn advance(&mut self, input: &'src [u8]) -> Option<&'src [u8]>;
+    fn split<'a>(&self, input: &'src [u8], at: &'a usize) -> (&'src [u8], &'a usize);
+}
+
+#[extern_trait(direct, DiskProxy)]
+trait Disk {
+    type Error: Display;
+
+    fn new(id: u32) -> Self;
+    fn check(&self) -> Result<u32, Self::Error>;
+    #[future(size = 32)]
+    async fn read(&self) -> u32;
+}
+
+#[extern_trait(direct, service, Clock)]
+trait ClockIf {
+    fn now() -> u64;
+}
+
+mod impls {
+    use super::*;
+
+    #[derive(Debug)]
+    struct VecStack(Vec<u32>);
+
+    #[extern_trait]
+    impl Stack for VecStack {
+        fn new() -> Self {
+            VecStack(Vec::new())
+        }
+
+        fn push(&mut self, value: u32) {
+            self.0.push(value);
+        }
+
+        fn pop(&mut self) -> Option<u32> {
+            self.0.pop()
+        }
+
+        fn top(&self) -> Option<&u32> {
+            self.0.last()
+        }
+
+        fn merge(mut self, other: Self) -> Self {
+            self.0.extend(other.0);
+            self
+        }
+
+        fn sum<T: TryFrom<u64>>(&self) -> Option<T> {
+            T::try_from(self.0.iter().map(|&v| u64::from(v)).sum()).ok()
+        }
+
+        unsafe fn get_unchecked(&self, index: usize) -> u32 {
+            unsafe { *self.0.get_unchecked(index) }
+        }
+    }
+
+    struct Pos(usize);
+
+    #[extern_trait]
+    impl<'src> Cursor<'src> for Pos {
+        fn new() -> Self {
+            Pos(0)
+        }
+
+        fn advance(&mut self, input: &'src [u8]) -> Option<&'src [u8]> {
+            let rest = input.get(self.0..)?;
+            self.0 += 1;
+            Some(rest)
+        }
+
+        fn split<'a>(&self, input: &'src [u8], at: &'a usize) -> (&'src [u8], &'a usize) {
+            (&input[..self.0], at)
+        }
+    }
+
+    struct Nvme(u32);
+
+    struct NvmeError(u32);
+
+    impl Display for NvmeError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "nvme{} failed", self.0)
+        }
+    }
+
+    #[extern_trait]
+    impl Disk for Nvme {
+        type Error = NvmeError;
+
+        fn new(id: u32) -> Self {
+            Nvme(id)
+        }
+
+        fn check(&self) -> Result<u32, Self::Error> {
+            Err(NvmeError(self.0))
+        }
+
+        async fn read(&self) -> u32 {
+            self.0 * 2
+        }
+    }
+
+    struct Tsc;
+
+    #[extern_trait]
+    impl ClockIf for Tsc {
+        fn now() -> u64 {
+            42
+        }
+    }
+}
+
+// Each method is exported under its own symbol.
+unsafe extern "Rust" {
+    #[link_name = "direct_calls_stack$push"]
+    static STACK_PUSH: u8;
+    #[link_name = "direct_calls_stack$__sum_1"]
+    static STACK_SUM_U64: u8;
+}
+
+#[test]
+fn direct_calls() {
+    let mut stack = StackProxy::new();
+    stack.push(1);
+    stack.push(2);
+    let mut other = StackProxy::new();
+    other.push(300);
+
+    let mut stack = stack.merge(other);
+    assert_eq!(stack.top(), Some(&300));
+    assert_eq!(stack.sum::<u64>(), Some(303));
+    assert_eq!(stack.sum::<u8>(), None);
+    assert_eq!(unsafe { stack.get_unchecked(1) }, 2);
+    assert_eq!(stack.pop(), Some(300));
+    assert_eq!(format!("{stack:?}"), "VecStack([1, 2])");
+
+    let input = b"abc";
+    let mut cursor = CursorProxy::new();
+    assert_eq!(cursor.advance(input), Some(&b"abc"[..]));
+    assert_eq!(cursor.split(input, &7), (&b"a"[..], &7));
+
+    let disk = DiskProxy::new(3);
+    let err = disk.check().err().unwrap();
+    assert_eq!(err.to_string(), "nvme3 failed");
+    let mut cx = Context::from_waker(Waker::noop());
+    assert_eq!(pin!(disk.read()).poll(&mut cx), Poll::Ready(6));
+
+    assert_eq!(Clock::now(), 42);
+
+    assert!(!(&raw const STACK_PUSH).is_null());
+    assert!(!(&raw const STACK_SUM_U64).is_null());
+}
@@ -0,0 +1,56 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(symbol = "acme_console$vtable.v1", ConsoleProxy)]
+trait Console {
+    fn new(width: usize) -> Self;
+    fn width(&self) -> usize;
+}
+
+#[extern_trait(service, symbol = "acme_clock", Clock)]
+trait ClockIf {
+    fn now() -> u64;
+}
+
+mod impls {
+    use super::*;
+
+    struct Vga(usize);
+
+    #[extern_trait]
+    impl Console for Vga {
+        fn new(width: usize) -> Self {
+            Vga(width)
+        }
+
+        fn width(&self) -> usize {
+            self.0
+        }
+    }
+
+    struct Tsc;
+
+    #[extern_trait]
+    impl ClockIf for Tsc {
+        fn now() -> u64 {
+            7
+        }
+    }
+}
+
+// Objects from other build systems link against the names as written.
+unsafe extern "Rust" {
+    #[link_name = "acme_console$vtable.v1"]
+    static CONSOLE_VTABLE: u8;
+    #[link_name = "acme_clock"]
+    static CLOCK_VTABLE: u8;
+}
+
+#[test]
+fn explicit_symbols() {
+    assert_eq!(ConsoleProxy::new(80).width(), 80);
+    assert_eq!(Clock::now(), 7);
+
+    assert!(!(&raw const CONSOLE_VTABLE).is_null());
+    assert!(!(&raw const CLOCK_VTABLE).is_null());
+    assert!(extern_trait::symbol::decode("acme_clock").is_none());
+}
@@ -0,0 +1,111 @@
+use std::panic::{AssertUnwindSafe, catch_unwind};
+
+use extern_trait::extern_trait;
+
+#[extern_trait(SensorProxy)]
+trait Sensor {
+    extern "C" fn new(id: u32) -> Self;
+    extern "C" fn id(&self) -> u32;
+    extern "C-unwind" fn read(&self) -> u32;
+    unsafe extern "C" fn calibrate(&mut self, offset: *const u32);
+    #[allow(missing_abi)]
+    extern "C" fn reset(&mut self);
+    #[instances(u8, u32)]
+    extern "C" fn scaled<T: From<u8>>(&self, scale: u8) -> T;
+    fn name(&self) -> &'static str;
+}
+
+#[extern_trait(direct, service, Firmware)]
+trait FirmwareIf {
+    extern "C" fn version() -> u32;
+    extern "C-unwind" fn fail();
+}
+
+mod impls {
+    use super::*;
+
+    #[repr(C)]
+    struct Thermometer(u32, u32);
+
+    #[extern_trait]
+    impl Sensor for Thermometer {
+        extern "C" fn new(id: u32) -> Self {
+            Thermometer(id, 20)
+        }
+
+        extern "C" fn id(&self) -> u32 {
+            self.0
+        }
+
+        extern "C-unwind" fn read(&self) -> u32 {
+            assert!(self.1 < 100, "sensor {} overheated", self.0);
+            self.1
+        }
+
+        unsafe extern "C" fn calibrate(&mut self, offset: *const u32) {
+            self.1 += unsafe { *offset };
+        }
+
+        extern "C" fn reset(&mut self) {
+            self.1 = 20;
+        }
+
+        extern "C" fn scaled<T: From<u8>>(&self, scale: u8) -> T {
+            T::from(self.1 as u8 * scale)
+        }
+
+        fn name(&self) -> &'static str {
+            "thermometer"
+        }
+    }
+
+    struct Rom;
+
+    #[extern_trait]
+    impl FirmwareIf for Rom {
+        extern "C" fn version() -> u32 {
+            3
+        }
+
+        extern "C-unwind" fn fail() {
+            panic!("firmware failure");
+        }
+    }
+}
+
+#[test]
+fn extern_methods() {
+    let mut sensor = SensorProxy::new(7);
+    assert_eq!(sensor.id(), 7);
+    assert_eq!(sensor.read(), 20);
+    unsafe { sensor.calibrate(&5) };
+    assert_eq!(sensor.read(), 25);
+    assert_eq!(sensor.scaled::<u32>(2), 50);
+    assert_eq!(sensor.scaled::<u8>(3), 75);
+    sensor.reset();
+    assert_eq!(sensor.read(), 20);
+    assert_eq!(sensor.name(), "thermometer");
+
+    assert_eq!(Firmware::version(), 3);
+}
+
+#[test]
+fn callbacks() {
+    // The proxy's methods can be handed out as C callbacks
+    let read: extern "C-unwind" fn(&SensorProxy) -> u32 = <SensorProxy as Sensor>::read;
+    let version: extern "C" fn() -> u32 = Firmware::version;
+    let sensor = SensorProxy::new(1);
+    assert_eq!(read(&sensor), 20);
+    assert_eq!(version(), 3);
+}
+
+#[test]
+fn unwinding() {
+    let mut sensor = SensorProxy::new(2);
+    unsafe { sensor.calibrate(&200) };
+    let err = catch_unwind(AssertUnwindSafe(|| sensor.read())).unwrap_err();
+    assert_eq!(err.downcast_ref::<String>().unwrap(), "sensor 2 overheated");
+
+    let err = catch_unwind(|| Firmware::fail()).unwrap_err();
+    assert_eq!(*err.downcast_ref::<&str>().unwrap(), "firmware failure");
+}
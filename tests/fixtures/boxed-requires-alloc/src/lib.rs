@@ -0,0 +1,15 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(BufferProxy)]
+trait Buffer {
+    fn new() -> Self;
+}
+
+struct Large([u8; 64]);
+
+#[extern_trait(boxed)]
+impl Buffer for Large {
+    fn new() -> Self {
+        Self([0; 64])
+    }
+}
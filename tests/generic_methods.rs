@@ -0,0 +1,68 @@
+use extern_trait::extern_trait;
+
+pub trait Pod: Copy + Into<u64> {}
+
+impl Pod for u8 {}
+impl Pod for u16 {}
+impl Pod for u32 {}
+impl Pod for u64 {}
+
+#[extern_trait(RegsProxy)]
+trait Regs {
+    fn new() -> Self;
+    #[instances(u8, u16, u32, u64)]
+    fn write<T: Pod>(&mut self, v: T) -> usize;
+    #[instances(u8, u32)]
+    fn read<T: TryFrom<u64>>(&self) -> Option<T>;
+    #[instances(u8, u32)]
+    fn nth<'a, T: Copy>(&self, items: &'a [T]) -> Option<&'a T>;
+    fn total(&self) -> u64;
+}
+
+mod regs_impl {
+    use super::*;
+
+    struct Bus(u64);
+
+    #[extern_trait]
+    impl Regs for Bus {
+        fn new() -> Self {
+            Bus(0)
+        }
+
+        fn write<T: Pod>(&mut self, v: T) -> usize {
+            self.0 += v.into();
+            size_of::<T>()
+        }
+
+        fn read<T: TryFrom<u64>>(&self) -> Option<T> {
+            T::try_from(self.0).ok()
+        }
+
+        fn nth<'a, T: Copy>(&self, items: &'a [T]) -> Option<&'a T> {
+            items.get(self.0 as usize)
+        }
+
+        fn total(&self) -> u64 {
+            self.0
+        }
+    }
+}
+
+#[test]
+fn generic_methods_dispatch_per_instance() {
+    let mut regs = RegsProxy::new();
+    assert_eq!(regs.write(1u8), 1);
+    assert_eq!(regs.write(2u16), 2);
+    assert_eq!(regs.write(3u32), 4);
+    assert_eq!(regs.write(4u64), 8);
+    assert_eq!(regs.total(), 10);
+    assert_eq!(regs.read::<u8>(), Some(10));
+    assert_eq!(regs.read::<u32>(), Some(10));
+
+    assert_eq!(regs.nth(&[0u8; 16]), Some(&0));
+    assert_eq!(regs.nth(&[0u32; 4]), None);
+
+    regs.write(u32::MAX);
+    assert_eq!(regs.read::<u8>(), None);
+}
@@ -0,0 +1,62 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(instances = [Codec<u32>, Codec<String>], CodecProxy)]
+trait Codec<T>: Clone {
+    fn new() -> Self;
+    fn encode(&self, value: &T) -> Vec<u8>;
+    fn decode(&self, bytes: &[u8]) -> Option<T>;
+}
+
+mod codec_impl {
+    use super::*;
+
+    #[derive(Clone)]
+    struct Le;
+
+    #[extern_trait]
+    impl Codec<u32> for Le {
+        fn new() -> Self {
+            Le
+        }
+
+        fn encode(&self, value: &u32) -> Vec<u8> {
+            value.to_le_bytes().to_vec()
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Option<u32> {
+            Some(u32::from_le_bytes(bytes.try_into().ok()?))
+        }
+    }
+
+    // Exported by the impl of `Codec<u32>`
+    impl Codec<String> for Le {
+        fn new() -> Self {
+            Le
+        }
+
+        fn encode(&self, value: &String) -> Vec<u8> {
+            value.as_bytes().to_vec()
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Option<String> {
+            String::from_utf8(bytes.to_vec()).ok()
+        }
+    }
+}
+
+#[test]
+fn proxy_implements_every_instantiation() {
+    let codec = <CodecProxy as Codec<u32>>::new().clone();
+
+    let bytes = Codec::<u32>::encode(&codec, &0x0403_0201);
+    assert_eq!(bytes, [1, 2, 3, 4]);
+    assert_eq!(Codec::<u32>::decode(&codec, &bytes), Some(0x0403_0201));
+    assert_eq!(Codec::<u32>::decode(&codec, &[1]), None);
+
+    let bytes = codec.encode(&"hi".to_string());
+    assert_eq!(bytes, b"hi");
+    assert_eq!(
+        Codec::<String>::decode(&codec, &bytes).as_deref(),
+        Some("hi")
+    );
+}
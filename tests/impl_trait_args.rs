@@ -0,0 +1,114 @@
+use std::{
+    fmt::{self, Write},
+    future::Future,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+use extern_trait::extern_trait;
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    pub name: &'static str,
+    pub size: u64,
+}
+
+#[extern_trait(ArchiveProxy)]
+trait Archive {
+    fn new() -> Self;
+    fn visit(&self, f: impl FnMut(&Entry));
+    fn find(&self, pred: impl Fn(&Entry) -> bool + Send) -> Option<&Entry>;
+    fn total(&self, extra: impl Iterator<Item = u64>) -> u64;
+    fn describe(&self, out: impl Write, sep: impl fmt::Display) -> fmt::Result;
+    fn with_prefix<'a>(&self, prefix: &'a str, f: impl FnMut(&'a str, &str));
+    #[future(size = 64)]
+    async fn visit_async(&self, f: impl FnMut(&Entry));
+}
+
+mod archive_impl {
+    use super::*;
+
+    struct Tar(&'static [Entry]);
+
+    #[extern_trait]
+    impl Archive for Tar {
+        fn new() -> Self {
+            Tar(&[Entry { name: "a", size: 1 }, Entry { name: "b", size: 2 }])
+        }
+
+        fn visit(&self, f: impl FnMut(&Entry)) {
+            self.0.iter().for_each(f);
+        }
+
+        fn find(&self, pred: impl Fn(&Entry) -> bool + Send) -> Option<&Entry> {
+            self.0.iter().find(|entry| pred(entry))
+        }
+
+        fn total(&self, extra: impl Iterator<Item = u64>) -> u64 {
+            self.0.iter().map(|entry| entry.size).chain(extra).sum()
+        }
+
+        fn describe(&self, mut out: impl Write, sep: impl fmt::Display) -> fmt::Result {
+            for (i, entry) in self.0.iter().enumerate() {
+                if i > 0 {
+                    write!(out, "{sep}")?;
+                }
+                write!(out, "{}", entry.name)?;
+            }
+            Ok(())
+        }
+
+        fn with_prefix<'a>(&self, prefix: &'a str, mut f: impl FnMut(&'a str, &str)) {
+            for entry in self.0 {
+                f(prefix, entry.name);
+            }
+        }
+
+        async fn visit_async(&self, f: impl FnMut(&Entry)) {
+            self.0.iter().for_each(f);
+        }
+    }
+}
+
+#[test]
+fn closure_args() {
+    let archive = ArchiveProxy::new();
+    let mut total = 0;
+    archive.visit(|entry| total += entry.size);
+    assert_eq!(total, 3);
+
+    let found = archive.find(|entry| entry.size == 2);
+    assert_eq!(found.map(|entry| entry.name), Some("b"));
+
+    assert_eq!(archive.total([5, 6].into_iter()), 14);
+
+    let mut names = Vec::new();
+    archive.with_prefix("/", |prefix, name| names.push(format!("{prefix}{name}")));
+    assert_eq!(names, ["/a", "/b"]);
+}
+
+#[test]
+fn trait_args() {
+    let archive = ArchiveProxy::new();
+    let mut out = String::new();
+    archive.describe(&mut out, ", ").unwrap();
+    assert_eq!(out, "a, b");
+}
+
+#[test]
+fn async_closure_args() {
+    let archive = ArchiveProxy::new();
+    let mut names = Vec::new();
+    block_on(archive.visit_async(|entry| names.push(entry.name)));
+    assert_eq!(names, ["a", "b"]);
+}
@@ -0,0 +1,39 @@
+#![cfg(debug_assertions)]
+
+use extern_trait::{__private::ConstTypeId, extern_trait};
+
+// Implemented only by the foreign VTable below
+#[extern_trait(service, symbol = "layout_check_greeter", Greeter)]
+#[allow(dead_code)]
+trait GreeterIf {
+    fn greet() -> u32;
+}
+
+// An implementation built elsewhere against another version of the trait,
+// exported under the same explicit symbol.
+#[repr(C)]
+#[allow(dead_code)]
+struct ForeignVTable {
+    typeid: ConstTypeId,
+    drop: unsafe fn(*mut ()),
+    layout_hash: u64,
+    layout_version: &'static str,
+}
+
+#[unsafe(export_name = "layout_check_greeter")]
+static FOREIGN_VTABLE: ForeignVTable = ForeignVTable {
+    typeid: ConstTypeId::of::<()>(),
+    drop: |_| {},
+    layout_hash: 0x0123_4567_89ab_cdef,
+    layout_version: "greeter-api 0.9.0",
+};
+
+#[test]
+fn mismatched_layout() {
+    let err = std::panic::catch_unwind(Greeter::greet).unwrap_err();
+    let msg = err.downcast_ref::<String>().unwrap();
+    let proxy = format!("built against extern-trait {}", env!("CARGO_PKG_VERSION"));
+    assert!(msg.contains("VTable layout mismatch for trait `GreeterIf`"));
+    assert!(msg.contains(&proxy));
+    assert!(msg.ends_with("implementation against greeter-api 0.9.0 (layout 0123456789abcdef)"));
+}
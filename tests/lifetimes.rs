@@ -0,0 +1,104 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(TokenizerProxy)]
+trait Tokenizer {
+    fn new() -> Self;
+    fn first_word<'a>(&self, input: &'a str) -> &'a str;
+    fn longest<'a, 'b>(&'a self, a: &'b str, b: &'b str) -> (&'a str, &'b str);
+    fn each(&self, input: &str, f: &mut dyn for<'a> FnMut(&'a str));
+    fn name(&self) -> &str;
+    fn describe(&self, input: &str) -> (&str, usize);
+}
+
+#[extern_trait(CursorProxy)]
+trait Cursor<'src> {
+    fn new() -> Self;
+    fn advance(&mut self, input: &'src [u8]) -> Option<&'src [u8]>;
+    fn split<'a>(&self, input: &'src [u8], at: &'a usize) -> (&'src [u8], &'a usize);
+}
+
+mod tokenizer_impl {
+    use super::*;
+
+    struct Whitespace;
+
+    #[extern_trait]
+    impl Tokenizer for Whitespace {
+        fn new() -> Self {
+            Whitespace
+        }
+
+        fn first_word<'a>(&self, input: &'a str) -> &'a str {
+            input.split_whitespace().next().unwrap_or("")
+        }
+
+        fn longest<'a, 'b>(&'a self, a: &'b str, b: &'b str) -> (&'a str, &'b str) {
+            (self.name(), if b.len() > a.len() { b } else { a })
+        }
+
+        fn each(&self, input: &str, f: &mut dyn for<'a> FnMut(&'a str)) {
+            input.split_whitespace().for_each(f);
+        }
+
+        fn name(&self) -> &str {
+            "whitespace"
+        }
+
+        fn describe(&self, input: &str) -> (&str, usize) {
+            (self.name(), input.split_whitespace().count())
+        }
+    }
+
+    struct Pos(usize);
+
+    #[extern_trait]
+    impl<'src> Cursor<'src> for Pos {
+        fn new() -> Self {
+            Pos(0)
+        }
+
+        fn advance(&mut self, input: &'src [u8]) -> Option<&'src [u8]> {
+            let rest = input.get(self.0..)?;
+            self.0 += 1;
+            Some(rest)
+        }
+
+        fn split<'a>(&self, input: &'src [u8], at: &'a usize) -> (&'src [u8], &'a usize) {
+            (&input[self.0 + *at..], at)
+        }
+    }
+}
+
+#[test]
+fn method_lifetimes() {
+    let tokenizer = TokenizerProxy::new();
+    let input = String::from("hello world");
+    assert_eq!(tokenizer.first_word(&input), "hello");
+
+    let (name, longest) = tokenizer.longest("ab", "abc");
+    assert_eq!(name, "whitespace");
+    assert_eq!(longest, "abc");
+
+    let mut words = Vec::new();
+    tokenizer.each("a b c", &mut |word| words.push(word.to_owned()));
+    assert_eq!(words, ["a", "b", "c"]);
+    assert_eq!(tokenizer.name(), "whitespace");
+    assert_eq!(tokenizer.describe(&input), ("whitespace", 2));
+}
+
+#[test]
+fn trait_lifetimes() {
+    let data = vec![1u8, 2, 3];
+    let mut cursor = CursorProxy::new();
+    assert_eq!(cursor.advance(&data), Some(&[1, 2, 3][..]));
+    assert_eq!(cursor.advance(&data), Some(&[2, 3][..]));
+
+    let at = 0;
+    let (rest, at) = cursor.split(&data, &at);
+    assert_eq!(rest, &[3]);
+    assert_eq!(*at, 0);
+
+    // The same proxy works with input of any lifetime.
+    let other = [9u8; 4];
+    assert_eq!(cursor.advance(&other), Some(&[9, 9][..]));
+}
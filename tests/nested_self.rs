@@ -0,0 +1,102 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use extern_trait::extern_trait;
+
+static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+#[extern_trait(CellProxy)]
+trait Cell: Sized {
+    fn try_new(value: u32) -> Result<Self, String>;
+    fn find(value: u32) -> core::option::Option<Self>;
+    fn split(self) -> (Self, Self);
+    fn triple(value: u32) -> [Self; 3];
+    fn join(cells: (Self, Option<Self>)) -> Self;
+    fn sum(cells: [Self; 2]) -> u32;
+    fn check(self) -> std::result::Result<Self, Self>;
+    fn value(&self) -> u32;
+}
+
+mod cell_impl {
+    use super::*;
+
+    struct Counter(u32);
+
+    impl Drop for Counter {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[extern_trait]
+    impl Cell for Counter {
+        fn try_new(value: u32) -> Result<Self, String> {
+            if value < 100 {
+                Ok(Counter(value))
+            } else {
+                Err(format!("{value} is too large"))
+            }
+        }
+
+        fn find(value: u32) -> Option<Self> {
+            value.is_multiple_of(2).then_some(Counter(value))
+        }
+
+        fn split(self) -> (Self, Self) {
+            (Counter(self.0 / 2), Counter(self.0 - self.0 / 2))
+        }
+
+        fn triple(value: u32) -> [Self; 3] {
+            [Counter(value), Counter(value + 1), Counter(value + 2)]
+        }
+
+        fn join(cells: (Self, Option<Self>)) -> Self {
+            let (a, b) = cells;
+            Counter(a.0 + b.map_or(0, |b| b.0))
+        }
+
+        fn sum(cells: [Self; 2]) -> u32 {
+            cells.iter().map(|c| c.0).sum()
+        }
+
+        fn check(self) -> Result<Self, Self> {
+            if self.0 > 0 { Ok(self) } else { Err(self) }
+        }
+
+        fn value(&self) -> u32 {
+            self.0
+        }
+    }
+}
+
+#[test]
+fn nested_self_round_trips() {
+    let cell = CellProxy::try_new(7).unwrap();
+    assert_eq!(cell.value(), 7);
+    assert_eq!(
+        CellProxy::try_new(100).err().as_deref(),
+        Some("100 is too large")
+    );
+
+    assert_eq!(CellProxy::find(4).map(|c| c.value()), Some(4));
+    assert!(CellProxy::find(3).is_none());
+
+    let (a, b) = cell.split();
+    assert_eq!((a.value(), b.value()), (3, 4));
+
+    let [x, y, z] = CellProxy::triple(10);
+    assert_eq!([x.value(), y.value(), z.value()], [10, 11, 12]);
+
+    assert_eq!(CellProxy::join((a, Some(b))).value(), 7);
+    assert_eq!(CellProxy::join((x, None)).value(), 10);
+    assert_eq!(CellProxy::sum([y, z]), 23);
+
+    assert!(CellProxy::try_new(1).unwrap().check().is_ok());
+    assert!(CellProxy::try_new(0).unwrap().check().is_err());
+
+    DROPS.store(0, Ordering::SeqCst);
+    drop(CellProxy::triple(0));
+    assert_eq!(DROPS.load(Ordering::SeqCst), 3);
+    // `split` consumes the original cell and returns two new ones.
+    drop(CellProxy::try_new(5).unwrap().split());
+    assert_eq!(DROPS.load(Ordering::SeqCst), 6);
+}
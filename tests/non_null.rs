@@ -0,0 +1,120 @@
+#![cfg(feature = "alloc")]
+
+use std::{mem::size_of, sync::Arc};
+
+use extern_trait::{NonNullPointer, extern_trait};
+
+#[extern_trait(non_null, NodeProxy)]
+trait Node: Sized {
+    type Payload;
+
+    fn new(value: u32) -> Self;
+    fn payload(&self) -> Self::Payload;
+    fn value(&self) -> u32;
+    fn next(&self) -> Option<Self>;
+}
+
+#[extern_trait(non_null, pub NameProxy)]
+trait Name: Sized {
+    fn lookup(index: usize) -> Option<Self>;
+    fn get(&self) -> &str;
+}
+
+#[extern_trait(non_null, WideProxy)]
+trait Wide: Send + Sync {
+    fn new(values: &[u8]) -> Self;
+    fn len(&self) -> usize;
+}
+
+mod node_impl {
+    use super::*;
+
+    #[repr(transparent)]
+    struct BoxNode(Box<u32>);
+
+    unsafe impl NonNullPointer for BoxNode {}
+
+    #[extern_trait]
+    impl Node for BoxNode {
+        type Payload = Arc<String>;
+
+        fn new(value: u32) -> Self {
+            BoxNode(Box::new(value))
+        }
+
+        fn payload(&self) -> Arc<String> {
+            Arc::new(self.0.to_string())
+        }
+
+        fn value(&self) -> u32 {
+            *self.0
+        }
+
+        fn next(&self) -> Option<Self> {
+            (*self.0 > 0).then(|| BoxNode(Box::new(*self.0 - 1)))
+        }
+    }
+}
+
+mod name_impl {
+    use super::*;
+
+    static NAMES: [&str; 2] = ["alpha", "beta"];
+
+    #[extern_trait]
+    impl Name for &'static &'static str {
+        fn lookup(index: usize) -> Option<Self> {
+            NAMES.get(index)
+        }
+
+        fn get(&self) -> &str {
+            self
+        }
+    }
+}
+
+mod wide_impl {
+    use super::*;
+
+    #[extern_trait]
+    impl Wide for Arc<Vec<u8>> {
+        fn new(values: &[u8]) -> Self {
+            Arc::new(values.to_vec())
+        }
+
+        fn len(&self) -> usize {
+            Vec::len(self)
+        }
+    }
+}
+
+#[test]
+fn option_proxy_has_no_overhead() {
+    assert_eq!(size_of::<Option<NodeProxy>>(), size_of::<NodeProxy>());
+    assert_eq!(
+        size_of::<Option<NodeProxyPayload>>(),
+        size_of::<NodeProxyPayload>()
+    );
+    assert_eq!(size_of::<Option<NameProxy>>(), size_of::<NameProxy>());
+    assert_eq!(size_of::<Option<WideProxy>>(), size_of::<WideProxy>());
+}
+
+#[test]
+fn non_null_methods() {
+    let node = NodeProxy::new(2);
+    assert_eq!(node.value(), 2);
+    let _payload = Some(node.payload());
+    let mut list = Vec::new();
+    let mut next = Some(node);
+    while let Some(node) = next {
+        list.push(node.value());
+        next = node.next();
+    }
+    assert_eq!(list, [2, 1, 0]);
+
+    assert_eq!(NameProxy::lookup(1).unwrap().get(), "beta");
+    assert!(NameProxy::lookup(2).is_none());
+
+    let wide = Some(WideProxy::new(&[1, 2, 3]));
+    assert_eq!(wide.map(|wide| wide.len()), Some(3));
+}
@@ -0,0 +1,147 @@
+use std::{cell::Cell, fmt};
+
+use extern_trait::extern_trait;
+
+thread_local! {
+    static DROPPED: Cell<usize> = const { Cell::new(0) };
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub name: &'static str,
+    pub size: u64,
+}
+
+#[extern_trait(DirProxy)]
+trait Dir {
+    fn new() -> Self;
+    #[opaque(size = 32)]
+    fn entries(&self) -> impl Iterator<Item = Entry> + '_;
+    #[opaque(size = 32)]
+    fn names(&self) -> impl DoubleEndedIterator<Item = &str> + ExactSizeIterator;
+    #[opaque(size = 16)]
+    fn matching<'a>(&self, prefix: &'a str) -> impl Fn(&str) -> bool + 'a;
+    #[opaque(size = 16)]
+    fn counter(&self) -> impl FnMut() -> u32 + Send;
+    #[opaque(size = 16)]
+    fn summary(&self) -> impl fmt::Display + fmt::Debug;
+}
+
+mod dir_impl {
+    use super::*;
+
+    struct Tracked;
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            DROPPED.with(|dropped| dropped.set(dropped.get() + 1));
+        }
+    }
+
+    struct Listing(&'static [Entry]);
+
+    const ENTRIES: &[Entry] = &[
+        Entry {
+            name: "boot",
+            size: 4,
+        },
+        Entry {
+            name: "bin",
+            size: 16,
+        },
+        Entry {
+            name: "etc",
+            size: 1,
+        },
+    ];
+
+    #[derive(Debug)]
+    struct Summary(usize);
+
+    impl fmt::Display for Summary {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{} entries", self.0)
+        }
+    }
+
+    #[extern_trait]
+    impl Dir for Listing {
+        fn new() -> Self {
+            Listing(ENTRIES)
+        }
+
+        fn entries(&self) -> impl Iterator<Item = Entry> + '_ {
+            let tracked = Tracked;
+            self.0.iter().cloned().inspect(move |_| {
+                let _ = &tracked;
+            })
+        }
+
+        fn names(&self) -> impl DoubleEndedIterator<Item = &str> + ExactSizeIterator {
+            self.0.iter().map(|entry| entry.name)
+        }
+
+        fn matching<'a>(&self, prefix: &'a str) -> impl Fn(&str) -> bool + 'a {
+            move |name| name.starts_with(prefix)
+        }
+
+        fn counter(&self) -> impl FnMut() -> u32 + Send {
+            let mut count = 0;
+            move || {
+                count += 1;
+                count
+            }
+        }
+
+        fn summary(&self) -> impl fmt::Display + fmt::Debug {
+            Summary(self.0.len())
+        }
+    }
+}
+
+#[test]
+fn opaque_iterators() {
+    let dir = DirProxy::new();
+    let sizes: Vec<_> = dir.entries().map(|entry| entry.size).collect();
+    assert_eq!(sizes, [4, 16, 1]);
+
+    let mut names = dir.names();
+    assert_eq!(names.len(), 3);
+    assert_eq!(names.next_back(), Some("etc"));
+    assert_eq!(names.collect::<Vec<_>>(), ["boot", "bin"]);
+}
+
+#[test]
+fn opaque_closures() {
+    let dir = DirProxy::new();
+    let prefix = String::from("b");
+    let matches = dir.matching(&prefix);
+    let names: Vec<_> = dir.names().filter(|name| matches(name)).collect();
+    assert_eq!(names, ["boot", "bin"]);
+
+    let mut counter = dir.counter();
+    assert_eq!(counter(), 1);
+    assert_eq!(counter(), 2);
+    std::thread::scope(|scope| {
+        scope.spawn(move || assert_eq!(counter(), 3));
+    });
+}
+
+#[test]
+fn opaque_fmt() {
+    let dir = DirProxy::new();
+    let summary = dir.summary();
+    assert_eq!(summary.to_string(), "3 entries");
+    assert_eq!(format!("{summary:?}"), "Summary(3)");
+}
+
+#[test]
+fn opaque_dropped() {
+    DROPPED.with(|dropped| dropped.set(0));
+    let dir = DirProxy::new();
+    let mut entries = dir.entries();
+    entries.next();
+    assert_eq!(DROPPED.with(Cell::get), 0);
+    drop(entries);
+    assert_eq!(DROPPED.with(Cell::get), 1);
+}
@@ -0,0 +1,43 @@
+use std::pin::{Pin, pin};
+
+use extern_trait::extern_trait;
+
+#[extern_trait(CountdownProxy)]
+trait Countdown {
+    fn new(from: u32) -> Self;
+    fn next(self: Pin<&mut Self>) -> Option<u32>;
+    fn remaining(self: Pin<&Self>) -> u32;
+}
+
+mod countdown_impl {
+    use super::*;
+
+    struct Steps(u32);
+
+    #[extern_trait]
+    impl Countdown for Steps {
+        fn new(from: u32) -> Self {
+            Steps(from)
+        }
+
+        fn next(self: Pin<&mut Self>) -> Option<u32> {
+            let this = unsafe { self.get_unchecked_mut() };
+            this.0 = this.0.checked_sub(1)?;
+            Some(this.0)
+        }
+
+        fn remaining(self: Pin<&Self>) -> u32 {
+            self.0
+        }
+    }
+}
+
+#[test]
+fn pinned_receivers_point_at_inline_storage() {
+    let mut countdown = pin!(CountdownProxy::new(2));
+    assert_eq!(countdown.as_ref().remaining(), 2);
+    assert_eq!(countdown.as_mut().next(), Some(1));
+    assert_eq!(countdown.as_mut().next(), Some(0));
+    assert_eq!(countdown.as_mut().next(), None);
+    assert_eq!(countdown.as_ref().remaining(), 0);
+}
@@ -0,0 +1,128 @@
+use std::mem::{align_of, size_of};
+
+use extern_trait::extern_trait;
+
+#[extern_trait(size = 32, QueueProxy)]
+trait Queue {
+    fn new(handle: u64) -> Self;
+    fn push(&mut self, value: u8);
+    fn drain(&mut self) -> Vec<u8>;
+    fn handle(&self) -> u64;
+}
+
+#[extern_trait(align = 16, pub LanesProxy)]
+trait Lanes: Copy {
+    fn splat(value: u32) -> Self;
+    fn sum(&self) -> u32;
+    fn add(self, other: Self) -> Self;
+}
+
+#[extern_trait(size = 64, align = 32, BlockProxy)]
+trait Block {
+    fn new(fill: u8) -> Self;
+    fn bytes(&self) -> &[u8];
+}
+
+mod queue_impl {
+    use super::*;
+
+    struct VecQueue {
+        items: Vec<u8>,
+        handle: u64,
+    }
+
+    #[extern_trait]
+    impl Queue for VecQueue {
+        fn new(handle: u64) -> Self {
+            VecQueue {
+                items: Vec::new(),
+                handle,
+            }
+        }
+
+        fn push(&mut self, value: u8) {
+            self.items.push(value);
+        }
+
+        fn drain(&mut self) -> Vec<u8> {
+            std::mem::take(&mut self.items)
+        }
+
+        fn handle(&self) -> u64 {
+            self.handle
+        }
+    }
+}
+
+mod lanes_impl {
+    use super::*;
+
+    #[derive(Clone, Copy)]
+    #[repr(C, align(16))]
+    struct U32x4([u32; 4]);
+
+    #[extern_trait]
+    impl Lanes for U32x4 {
+        fn splat(value: u32) -> Self {
+            U32x4([value; 4])
+        }
+
+        fn sum(&self) -> u32 {
+            self.0.iter().sum()
+        }
+
+        fn add(self, other: Self) -> Self {
+            U32x4(std::array::from_fn(|i| self.0[i] + other.0[i]))
+        }
+    }
+}
+
+mod block_impl {
+    use super::*;
+
+    #[repr(C, align(32))]
+    struct Block64([u8; 64]);
+
+    #[extern_trait]
+    impl Block for Block64 {
+        fn new(fill: u8) -> Self {
+            Block64([fill; 64])
+        }
+
+        fn bytes(&self) -> &[u8] {
+            &self.0
+        }
+    }
+}
+
+#[test]
+fn sized_proxy() {
+    assert_eq!(size_of::<QueueProxy>(), 32);
+    assert_eq!(align_of::<QueueProxy>(), align_of::<extern_trait::Repr>());
+
+    let mut queue = QueueProxy::new(7);
+    queue.push(1);
+    queue.push(2);
+    assert_eq!(queue.drain(), [1, 2]);
+    assert_eq!(queue.handle(), 7);
+}
+
+#[test]
+fn aligned_proxy() {
+    assert_eq!(size_of::<LanesProxy>(), 16);
+    assert_eq!(align_of::<LanesProxy>(), 16);
+
+    let lanes = LanesProxy::splat(1);
+    let doubled = lanes.add(lanes);
+    assert_eq!(lanes.sum(), 4);
+    assert_eq!(doubled.sum(), 8);
+}
+
+#[test]
+fn sized_and_aligned_proxy() {
+    assert_eq!(size_of::<BlockProxy>(), 64);
+    assert_eq!(align_of::<BlockProxy>(), 32);
+
+    let block = BlockProxy::new(3);
+    assert_eq!(block.bytes(), [3; 64]);
+}
@@ -0,0 +1,100 @@
+#![cfg(all(feature = "section-defaults", not(feature = "nightly-weak")))]
+
+use ::extern_trait as renamed_extern_trait;
+use extern_trait::extern_trait;
+
+struct SectionDefaultCounter(u16);
+
+#[extern_trait(default = SectionDefaultCounter, pub SectionCounterProxy)]
+trait SectionCounter {
+    fn new(value: u16) -> Self;
+    fn value(&self) -> u16;
+    fn route(&self) -> &'static str;
+}
+
+impl SectionCounter for SectionDefaultCounter {
+    fn new(value: u16) -> Self {
+        Self(value + 10)
+    }
+
+    fn value(&self) -> u16 {
+        self.0
+    }
+
+    fn route(&self) -> &'static str {
+        "default"
+    }
+}
+
+struct RenamedDefaultCounter(u16);
+
+#[renamed_extern_trait::extern_trait(
+    default = RenamedDefaultCounter,
+    crate = renamed_extern_trait,
+    pub RenamedCounterProxy
+)]
+trait RenamedCounter {
+    fn new(value: u16) -> Self;
+    fn value(&self) -> u16;
+    fn route(&self) -> &'static str;
+}
+
+impl RenamedCounter for RenamedDefaultCounter {
+    fn new(value: u16) -> Self {
+        Self(value + 100)
+    }
+
+    fn value(&self) -> u16 {
+        self.0
+    }
+
+    fn route(&self) -> &'static str {
+        "renamed-default"
+    }
+}
+
+struct DefaultClock;
+
+#[extern_trait(default = DefaultClock, Clock)]
+trait ClockIf {
+    fn now() -> u64;
+}
+
+impl ClockIf for DefaultClock {
+    fn now() -> u64 {
+        0
+    }
+}
+
+// Unlike a weak default, a link section default may be overridden from
+// the defining crate itself.
+struct Tsc;
+
+#[extern_trait]
+impl ClockIf for Tsc {
+    fn now() -> u64 {
+        42
+    }
+}
+
+#[test]
+fn proxy_dispatches_to_section_default_when_no_impl_is_linked() {
+    let counter = SectionCounterProxy::new(32);
+
+    assert_eq!(counter.value(), 42);
+    assert_eq!(counter.route(), "default");
+}
+
+#[test]
+fn renamed_crate_path_and_default_type_dispatch_to_section_default() {
+    let counter = RenamedCounterProxy::new(23);
+
+    assert_eq!(counter.value(), 123);
+    assert_eq!(counter.route(), "renamed-default");
+}
+
+#[test]
+fn linked_impl_replaces_the_section_default() {
+    assert_eq!(Clock::now(), 42);
+    assert_eq!(Clock::now(), 42);
+}
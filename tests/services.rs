@@ -0,0 +1,126 @@
+use std::{
+    mem::size_of,
+    pin::pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use extern_trait::extern_trait;
+
+/// Monotonic time, provided by the platform.
+#[extern_trait(service, Time)]
+trait TimeIf: Send + Sync {
+    const TICKS_PER_SEC: u64;
+
+    /// Ticks since boot.
+    fn now() -> u64;
+    fn sleep(duration: Duration);
+    fn sleep_until(deadline: u64) -> Result<(), u64>;
+    unsafe fn set(ticks: u64);
+    #[future(size = 32)]
+    async fn wait(ticks: u64) -> u64;
+    #[instances(u32, u64)]
+    fn ticks_as<T: Copy>(ticks: u64) -> T;
+}
+
+#[extern_trait(service, LogService)]
+trait LogIf {
+    fn write(args: &str, sink: impl FnMut(&str)) -> usize;
+}
+
+mod time_impl {
+    use super::*;
+
+    static TICKS: AtomicU64 = AtomicU64::new(0);
+
+    struct TimeImpl;
+
+    #[extern_trait]
+    impl TimeIf for TimeImpl {
+        const TICKS_PER_SEC: u64 = 1000;
+
+        fn now() -> u64 {
+            TICKS.load(Ordering::SeqCst)
+        }
+
+        fn sleep(duration: Duration) {
+            TICKS.fetch_add(duration.as_millis() as u64, Ordering::SeqCst);
+        }
+
+        fn sleep_until(deadline: u64) -> Result<(), u64> {
+            let now = Self::now();
+            if deadline < now {
+                return Err(now);
+            }
+            TICKS.store(deadline, Ordering::SeqCst);
+            Ok(())
+        }
+
+        unsafe fn set(ticks: u64) {
+            TICKS.store(ticks, Ordering::SeqCst);
+        }
+
+        async fn wait(ticks: u64) -> u64 {
+            TICKS.fetch_add(ticks, Ordering::SeqCst) + ticks
+        }
+
+        fn ticks_as<T: Copy>(ticks: u64) -> T {
+            // SAFETY: only instantiated for `u32` and `u64`, read little-endian.
+            unsafe { std::mem::transmute_copy(&ticks) }
+        }
+    }
+}
+
+mod log_impl {
+    use super::*;
+
+    struct Stderr;
+
+    #[extern_trait]
+    impl LogIf for Stderr {
+        fn write(args: &str, mut sink: impl FnMut(&str)) -> usize {
+            sink(args);
+            args.len()
+        }
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+fn sleep_generic<T: TimeIf>(ms: u64) {
+    T::sleep(Duration::from_millis(ms));
+}
+
+#[test]
+fn service_functions() {
+    assert_eq!(size_of::<Time>(), 0);
+    assert_eq!(Time::ticks_per_sec(), 1000);
+
+    unsafe { Time::set(100) };
+    Time::sleep(Duration::from_millis(5));
+    assert_eq!(Time::now(), 105);
+    assert_eq!(Time::sleep_until(200), Ok(()));
+    assert_eq!(Time::sleep_until(150), Err(200));
+    assert_eq!(Time::ticks_as::<u32>(7), 7u32);
+    assert_eq!(Time::ticks_as::<u64>(7), 7u64);
+
+    sleep_generic::<Time>(10);
+    assert_eq!(<Time as TimeIf>::now(), 210);
+    assert_eq!(block_on(Time::wait(5)), 215);
+
+    let mut lines = Vec::new();
+    assert_eq!(
+        LogService::write("hello", |line| lines.push(line.to_owned())),
+        5
+    );
+    assert_eq!(lines, ["hello"]);
+}
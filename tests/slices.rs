@@ -0,0 +1,65 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(ScoreProxy)]
+trait Score: Sized {
+    fn new(value: usize) -> Self;
+    fn value(&self) -> usize;
+    fn sum(items: &[Self]) -> usize;
+    fn sort(items: &mut [Self]);
+    fn best(items: &[Self]) -> Option<&Self>;
+    fn window(items: &[Self], len: usize) -> &[Self];
+}
+
+mod score_impl {
+    use super::*;
+
+    // Slices of `Self` require exactly the layout of `Repr`.
+    #[repr(C, align(8))]
+    struct Points {
+        value: usize,
+        bonus: usize,
+    }
+
+    #[extern_trait]
+    impl Score for Points {
+        fn new(value: usize) -> Self {
+            Points { value, bonus: 0 }
+        }
+
+        fn value(&self) -> usize {
+            self.value + self.bonus
+        }
+
+        fn sum(items: &[Self]) -> usize {
+            items.iter().map(Self::value).sum()
+        }
+
+        fn sort(items: &mut [Self]) {
+            items.sort_by_key(Self::value);
+        }
+
+        fn best(items: &[Self]) -> Option<&Self> {
+            items.iter().max_by_key(|p| p.value())
+        }
+
+        fn window(items: &[Self], len: usize) -> &[Self] {
+            &items[..len.min(items.len())]
+        }
+    }
+}
+
+#[test]
+fn slices_of_self_are_reinterpreted() {
+    let mut scores: Vec<_> = [5, 1, 9, 3].into_iter().map(ScoreProxy::new).collect();
+    assert_eq!(ScoreProxy::sum(&scores), 18);
+    assert_eq!(ScoreProxy::best(&scores).map(ScoreProxy::value), Some(9));
+    assert!(ScoreProxy::best(&[]).is_none());
+
+    ScoreProxy::sort(&mut scores);
+    let values: Vec<_> = scores.iter().map(ScoreProxy::value).collect();
+    assert_eq!(values, [1, 3, 5, 9]);
+
+    let window = ScoreProxy::window(&scores, 2);
+    assert_eq!(window.len(), 2);
+    assert_eq!(ScoreProxy::sum(window), 4);
+}
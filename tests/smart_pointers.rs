@@ -0,0 +1,94 @@
+#![cfg(feature = "alloc")]
+
+use std::{
+    pin::Pin,
+    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use extern_trait::extern_trait;
+
+static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+#[extern_trait(ServiceProxy)]
+trait Service: Send + Sync + Sized {
+    fn new(name: &'static str) -> Self;
+    fn boxed(name: &'static str) -> Box<Self>;
+    fn into_name(self: Box<Self>) -> &'static str;
+    fn shared_name(self: Rc<Self>) -> &'static str;
+    fn name(self: Arc<Self>) -> &'static str;
+    fn pinned_name(self: Pin<Box<Self>>) -> &'static str;
+    fn names(items: Box<[Self]>) -> usize;
+}
+
+mod service_impl {
+    use super::*;
+
+    // Smart pointers of `Self` require exactly the layout of `Repr`.
+    #[repr(C, align(8))]
+    struct Named(&'static str);
+
+    impl Drop for Named {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[extern_trait]
+    impl Service for Named {
+        fn new(name: &'static str) -> Self {
+            Named(name)
+        }
+
+        fn boxed(name: &'static str) -> Box<Self> {
+            Box::new(Named(name))
+        }
+
+        fn into_name(self: Box<Self>) -> &'static str {
+            self.0
+        }
+
+        fn shared_name(self: Rc<Self>) -> &'static str {
+            self.0
+        }
+
+        fn name(self: Arc<Self>) -> &'static str {
+            self.0
+        }
+
+        fn pinned_name(self: Pin<Box<Self>>) -> &'static str {
+            self.0
+        }
+
+        fn names(items: Box<[Self]>) -> usize {
+            items.len()
+        }
+    }
+}
+
+#[test]
+fn smart_pointer_receivers_reinterpret_the_allocation() {
+    DROPS.store(0, Ordering::SeqCst);
+
+    assert_eq!(Box::new(ServiceProxy::new("box")).into_name(), "box");
+    assert_eq!(ServiceProxy::boxed("boxed").into_name(), "boxed");
+    assert_eq!(Box::pin(ServiceProxy::new("pin")).pinned_name(), "pin");
+    assert_eq!(DROPS.load(Ordering::SeqCst), 3);
+
+    let shared = Rc::new(ServiceProxy::new("rc"));
+    assert_eq!(shared.clone().shared_name(), "rc");
+    assert_eq!(DROPS.load(Ordering::SeqCst), 3);
+    assert_eq!(shared.shared_name(), "rc");
+    assert_eq!(DROPS.load(Ordering::SeqCst), 4);
+
+    let arc = Arc::new(ServiceProxy::new("arc"));
+    assert_eq!(arc.name(), "arc");
+    assert_eq!(DROPS.load(Ordering::SeqCst), 5);
+
+    let items: Box<[_]> = Box::new([ServiceProxy::new("a"), ServiceProxy::new("b")]);
+    assert_eq!(ServiceProxy::names(items), 2);
+    assert_eq!(DROPS.load(Ordering::SeqCst), 7);
+}
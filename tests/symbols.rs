@@ -0,0 +1,138 @@
+use extern_trait::{extern_trait, symbol};
+
+#[extern_trait(GreeterProxy)]
+trait Greeter {
+    fn new() -> Self;
+    fn greet(&self) -> &'static str;
+}
+
+#[extern_trait(instances = [Codec<u32>, Codec<(u8, bool)>], CodecProxy)]
+trait Codec<T> {
+    fn new() -> Self;
+    fn encode(&self, value: T) -> u64;
+}
+
+mod impls {
+    use super::*;
+
+    struct Hello;
+
+    #[extern_trait]
+    impl Greeter for Hello {
+        fn new() -> Self {
+            Hello
+        }
+
+        fn greet(&self) -> &'static str {
+            "hello"
+        }
+    }
+
+    struct Plain;
+
+    #[extern_trait]
+    impl Codec<u32> for Plain {
+        fn new() -> Self {
+            Plain
+        }
+
+        fn encode(&self, value: u32) -> u64 {
+            value.into()
+        }
+    }
+
+    impl Codec<(u8, bool)> for Plain {
+        fn new() -> Self {
+            Plain
+        }
+
+        fn encode(&self, (value, flag): (u8, bool)) -> u64 {
+            u64::from(value) << 1 | u64::from(flag)
+        }
+    }
+}
+
+#[test]
+fn proxy_symbols_decode() {
+    assert_eq!(GreeterProxy::new().greet(), "hello");
+    assert_eq!(
+        <CodecProxy as Codec<(u8, bool)>>::new().encode((1, true)),
+        3
+    );
+
+    let mut traits: Vec<_> = GreeterProxy::__VTABLE_SYMBOLS
+        .iter()
+        .chain(CodecProxy::__VTABLE_SYMBOLS)
+        .map(|s| {
+            let symbol = symbol::decode(s).unwrap();
+            assert_eq!(symbol.package(), "extern-trait");
+            assert_eq!(symbol.version(), env!("CARGO_PKG_VERSION"));
+            assert_eq!(symbol.crate_name(), "symbols");
+            (symbol.trait_name().to_string(), symbol.abi())
+        })
+        .collect();
+    traits.sort();
+    let names: Vec<_> = traits.iter().map(|(name, _)| name).collect();
+    assert_eq!(names, ["Codec<(u8,bool)>", "Codec<u32>", "Greeter"]);
+
+    // The instantiations differ in the type of `encode`
+    assert_ne!(traits[0].1, traits[1].1);
+}
+
+#[test]
+fn decode_escapes() {
+    let encoded = concat!(
+        "extern_trait$v2$my-api$1.0.0%2Bbuild.5$my_api$%C3%84pfel$",
+        "3b1e2cf7e1b3a3c7$0000000000000000$add-an-extern_trait-impl",
+    );
+    let hash = {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for part in ["my-api", "1.0.0+build.5", "my_api", "Äpfel"] {
+            for byte in part.bytes().chain([0]) {
+                hash = (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        }
+        hash
+    };
+    let encoded = encoded.replace("3b1e2cf7e1b3a3c7", &format!("{hash:016x}"));
+
+    let symbol = symbol::decode(&encoded).unwrap();
+    assert_eq!(symbol.version(), "1.0.0+build.5");
+    assert_eq!(symbol.version().as_escaped(), "1.0.0%2Bbuild.5");
+    assert_eq!(symbol.trait_name(), "Äpfel");
+    assert_eq!(
+        symbol.to_string(),
+        "trait `Äpfel` of crate `my_api` (my-api 1.0.0+build.5)"
+    );
+}
+
+#[test]
+fn decode_rejects_malformed() {
+    let valid = concat!(
+        "extern_trait$v2$my-api$0.1.0$my_api$Codec%3Cu32%3E$",
+        "276c58df34795f30$0123456789abcdef$add-an-extern_trait-impl",
+    );
+    assert!(symbol::decode(valid).is_some());
+
+    for invalid in [
+        String::new(),
+        "_ZN4core3fmt5write17h0123456789abcdefE".to_string(),
+        valid.replace("$v2$", "$v1$"),
+        // wrong hash
+        valid.replace("$0.1.0$", "$0.1.1$"),
+        // unescaped and needlessly escaped bytes
+        valid.replace("%3Cu32%3E", "<u32>"),
+        valid.replace("my-api", "my%2Dapi"),
+        // truncated escape, invalid UTF-8
+        valid.replace("%3Cu32%3E", "%3"),
+        valid.replace("Codec%3Cu32%3E", "%C3"),
+        // malformed fingerprint
+        valid.replace("0123456789abcdef", "0123456789ABCDEF"),
+        valid.replace("0123456789abcdef", "abcdef"),
+        // missing hint, extra part
+        valid.replace("$add-an-extern_trait-impl", ""),
+        format!("{valid}$x"),
+    ] {
+        assert!(symbol::decode(&invalid).is_none(), "{invalid}");
+    }
+}
@@ -0,0 +1,14 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(Proxy)]
+trait Config {
+    const LIMIT: usize;
+
+    fn new() -> Self;
+}
+
+const LIMIT: usize = <Proxy as Config>::LIMIT;
+
+fn main() {
+    let _ = LIMIT;
+}
@@ -0,0 +1,26 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(Proxy)]
+trait Api {
+    type Output;
+
+    fn new() -> Self;
+    fn output(&self) -> Self::Output;
+}
+
+struct Impl;
+
+#[extern_trait]
+impl Api for Impl {
+    type Output = [usize; 3];
+
+    fn new() -> Self {
+        Self
+    }
+
+    fn output(&self) -> Self::Output {
+        [0; 3]
+    }
+}
+
+fn main() {}
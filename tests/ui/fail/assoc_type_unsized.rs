@@ -0,0 +1,10 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(BadProxy)]
+trait Bad {
+    type Output: ?Sized;
+
+    fn new() -> Self;
+}
+
+fn main() {}
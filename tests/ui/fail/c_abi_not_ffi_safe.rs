@@ -0,0 +1,14 @@
+use extern_trait::extern_trait;
+
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[extern_trait(abi = "C", Proxy)]
+trait Canvas {
+    fn new() -> Self;
+    fn cursor(&self) -> Point;
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(abi = "C", Proxy)]
+trait Logger {
+    fn new() -> Self;
+    fn log(&self, message: &str);
+}
+
+fn main() {}
@@ -2,7 +2,7 @@ use extern_trait::extern_trait;
 
 #[extern_trait(BadProxy)]
 trait Bad {
-    fn nested(self: Box<Self>) -> Self;
+    fn nested(&self) -> Vec<Self>;
 }
 
 fn main() {}
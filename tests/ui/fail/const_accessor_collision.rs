@@ -0,0 +1,11 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(Proxy)]
+trait Buffer {
+    const LEN: usize;
+
+    fn new() -> Self;
+    fn len(&self) -> usize;
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(Proxy)]
+trait Shape {
+    const TYPE: u32;
+
+    fn new() -> Self;
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(direct, Proxy)]
+trait Console {
+    fn new() -> Self;
+}
+
+struct Uart;
+
+#[extern_trait(direct)]
+impl Console for Uart {
+    fn new() -> Self {
+        Uart
+    }
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(BadProxy)]
+trait Bad {
+    #[future(size = 16)]
+    async extern "C" fn value(&self) -> i32;
+}
+
+fn main() {}
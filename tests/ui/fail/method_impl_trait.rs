@@ -0,0 +1,8 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(BadProxy)]
+trait Bad {
+    fn values(&self) -> impl Iterator<Item = u32>;
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(PortProxy)]
+trait Port {
+    fn new() -> Self;
+    #[instances(u8, u16)]
+    fn write<T: Copy>(&self, v: T);
+}
+
+fn main() {
+    PortProxy::new().write(0u32);
+}
@@ -0,0 +1,8 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(BadProxy)]
+trait Bad {
+    fn pick<'a, 'b: 'a>(&self, a: &'a str, b: &'b str) -> &'a str;
+}
+
+fn main() {}
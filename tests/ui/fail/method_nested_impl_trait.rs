@@ -0,0 +1,8 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(BadProxy)]
+trait Bad {
+    fn visit(&self, f: Option<impl FnMut(u32)>);
+}
+
+fn main() {}
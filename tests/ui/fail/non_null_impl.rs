@@ -0,0 +1,17 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(non_null, Proxy)]
+trait Api {
+    fn new() -> Self;
+}
+
+struct Handle(usize);
+
+#[extern_trait]
+impl Api for Handle {
+    fn new() -> Self {
+        Self(1)
+    }
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use std::num::NonZeroU64;
+
+use extern_trait::extern_trait;
+
+#[extern_trait(non_null, Proxy)]
+trait Api {
+    fn new() -> Self;
+}
+
+#[extern_trait]
+impl Api for NonZeroU64 {
+    fn new() -> Self {
+        NonZeroU64::MIN
+    }
+}
+
+fn main() {}
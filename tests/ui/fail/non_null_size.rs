@@ -0,0 +1,8 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(non_null, size = 32, Proxy)]
+trait Api {
+    fn new() -> Self;
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(Proxy)]
+trait Device: Sized {
+    fn open() -> std::io::Result<Self>;
+}
+
+fn main() {}
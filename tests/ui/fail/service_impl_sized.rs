@@ -0,0 +1,17 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(service, Clock)]
+trait ClockIf {
+    fn now() -> u64;
+}
+
+struct Counter(u64);
+
+#[extern_trait]
+impl ClockIf for Counter {
+    fn now() -> u64 {
+        0
+    }
+}
+
+fn main() {}
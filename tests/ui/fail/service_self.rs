@@ -0,0 +1,9 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(service, Clock)]
+trait ClockIf {
+    fn now() -> u64;
+    fn elapsed(&self) -> u64;
+}
+
+fn main() {}
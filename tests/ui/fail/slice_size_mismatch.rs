@@ -0,0 +1,17 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(ScoreProxy)]
+trait Score: Sized {
+    fn sum(items: &[Self]) -> u64;
+}
+
+struct Small(u32);
+
+#[extern_trait]
+impl Score for Small {
+    fn sum(items: &[Self]) -> u64 {
+        items.iter().map(|s| s.0 as u64).sum()
+    }
+}
+
+fn main() {}
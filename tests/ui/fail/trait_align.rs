@@ -0,0 +1,8 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(align = 12, Proxy)]
+trait Api {
+    fn new() -> Self;
+}
+
+fn main() {}
@@ -2,7 +2,7 @@ use extern_trait::extern_trait;
 
 #[extern_trait(BadProxy)]
 trait Bad {
-    type Output;
+    items!();
     fn new() -> Self;
 }
 
@@ -0,0 +1,25 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(instances = [Codec<u32>, Codec<bool>], CodecProxy)]
+trait Codec<T> {
+    fn encode(&self, value: T) -> u64;
+}
+
+struct Wide(u64);
+
+#[extern_trait]
+impl Codec<u32> for Wide {
+    fn encode(&self, value: u32) -> u64 {
+        self.0 + value as u64
+    }
+}
+
+struct Flag;
+
+impl Codec<bool> for Flag {
+    fn encode(&self, value: bool) -> u64 {
+        value as u64
+    }
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(instances = [Codec<u32>], CodecProxy)]
+trait Codec<T> {
+    fn encode(&self, value: T) -> u64;
+}
+
+struct Raw;
+
+#[extern_trait]
+impl Codec<u8> for Raw {
+    fn encode(&self, value: u8) -> u64 {
+        value as u64
+    }
+}
+
+fn main() {}
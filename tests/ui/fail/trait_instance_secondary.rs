@@ -0,0 +1,24 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(instances = [Codec<u32>, Codec<bool>], CodecProxy)]
+trait Codec<T> {
+    fn encode(&self, value: T) -> u64;
+}
+
+struct Raw;
+
+#[extern_trait]
+impl Codec<u32> for Raw {
+    fn encode(&self, value: u32) -> u64 {
+        value as u64
+    }
+}
+
+#[extern_trait]
+impl Codec<bool> for Raw {
+    fn encode(&self, value: bool) -> u64 {
+        value as u64
+    }
+}
+
+fn main() {}
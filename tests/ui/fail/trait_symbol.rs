@@ -0,0 +1,8 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(symbol = "acme console", Proxy)]
+trait Console {
+    fn new() -> Self;
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use extern_trait::extern_trait;
+
+#[extern_trait(zero_sized, Proxy)]
+trait Rng {
+    fn new() -> Self;
+}
+
+struct Xorshift(u32);
+
+#[extern_trait]
+impl Rng for Xorshift {
+    fn new() -> Self {
+        Self(1)
+    }
+}
+
+fn main() {}
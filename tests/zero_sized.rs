@@ -0,0 +1,87 @@
+use std::{
+    mem::size_of,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use extern_trait::extern_trait;
+
+#[extern_trait(zero_sized, pub ClockProxy)]
+pub trait Clock: Copy + Send + Sync {
+    fn instance() -> Self;
+    fn now(&self) -> u64;
+    fn advance(&self, ticks: u64) -> u64;
+}
+
+#[extern_trait(zero_sized, ConsoleProxy)]
+trait Console {
+    type Lock;
+
+    fn get() -> Self;
+    fn lock(&self) -> Self::Lock;
+    fn write(&mut self, text: &str) -> usize;
+}
+
+mod clock_impl {
+    use super::*;
+
+    static TICKS: AtomicU64 = AtomicU64::new(0);
+
+    #[derive(Clone, Copy)]
+    struct MonotonicClock;
+
+    #[extern_trait]
+    impl Clock for MonotonicClock {
+        fn instance() -> Self {
+            MonotonicClock
+        }
+
+        fn now(&self) -> u64 {
+            TICKS.load(Ordering::SeqCst)
+        }
+
+        fn advance(&self, ticks: u64) -> u64 {
+            TICKS.fetch_add(ticks, Ordering::SeqCst) + ticks
+        }
+    }
+}
+
+mod console_impl {
+    use super::*;
+
+    struct Stdout;
+
+    #[extern_trait]
+    impl Console for Stdout {
+        type Lock = ();
+
+        fn get() -> Self {
+            Stdout
+        }
+
+        fn lock(&self) {}
+
+        fn write(&mut self, text: &str) -> usize {
+            text.len()
+        }
+    }
+}
+
+#[test]
+fn proxies_are_zero_sized() {
+    assert_eq!(size_of::<ClockProxy>(), 0);
+    assert_eq!(size_of::<ConsoleProxy>(), 0);
+    assert_eq!(size_of::<ConsoleProxyLock>(), 0);
+}
+
+#[test]
+fn zero_sized_dispatch() {
+    let clock = ClockProxy::instance();
+    let start = clock.now();
+    assert_eq!(clock.advance(5), start + 5);
+    let copy = clock;
+    assert!(copy.now() >= start + 5);
+
+    let mut console = ConsoleProxy::get();
+    let _lock = console.lock();
+    assert_eq!(console.write("hello"), 5);
+}